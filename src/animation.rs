@@ -1,98 +1,216 @@
+use crate::ron_asset::RonAssetLoader;
 use bevy::math::prelude::*;
-use bevy::math::vec2;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
 use num_traits::float::FloatConst;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 #[derive(Resource)]
 pub struct MousePosition(pub Vec2);
 
-#[derive(Component)]
+/// What a clip does after its last frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoopMode {
+    #[default]
+    Loop,
+    Once,     // back to the first frame and stop
+    PingPong, // back and forth, without showing the end frames twice
+    HoldLast, // stop on the last frame
+}
+
+/// A named animation in an atlas.
+pub struct AnimationClip {
+    pub name: String,
+    pub animation: RustAnimation,
+    pub restart: bool, // start from the first frame whenever it's entered
+}
+
+/// Going from one clip to another plays `via` first, `*` matches any clip.
+/// `via` should stop by itself, with `LoopMode::Once` or `HoldLast`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnimationTransition {
+    pub from: String,
+    pub to: String,
+    pub via: String,
+}
+
+#[derive(Component, Default)]
 pub struct RustAnimationAtlas {
-    animations: Vec<RustAnimation>,
+    clips: Vec<AnimationClip>,
+    transitions: Vec<AnimationTransition>,
     current: usize,
+    queued: Option<usize>, // clip to play once the transition clip is done
 }
 impl RustAnimationAtlas {
-    pub fn new(animations: impl Into<Vec<RustAnimation>>) -> Self {
-        Self {
-            animations: animations.into(),
-            current: 0,
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The first clip added is the one playing to begin with.
+    pub fn with_clip(mut self, name: impl Into<String>, animation: RustAnimation) -> Self {
+        self.clips.push(AnimationClip {
+            name: name.into(),
+            animation,
+            restart: true,
+        });
+        self
+    }
+    /// The last clip added carries on from where it was left instead of restarting.
+    pub fn keep_frame(mut self) -> Self {
+        if let Some(clip) = self.clips.last_mut() {
+            clip.restart = false;
         }
+        self
     }
-    pub fn current(&self) -> usize {
-        if let Some(anim) = self.animations.get(self.current) {
-            anim.current()
-        } else {
-            panic!("Animation index out of bounds in atlas.")
+    pub fn with_transition(
+        mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        via: impl Into<String>,
+    ) -> Self {
+        self.transitions.push(AnimationTransition {
+            from: from.into(),
+            to: to.into(),
+            via: via.into(),
+        });
+        self
+    }
+    fn find(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+    /// Name of the clip playing, which may be a transition on the way to another.
+    pub fn current_clip(&self) -> Option<&str> {
+        self.clips.get(self.current).map(|clip| clip.name.as_str())
+    }
+    /// Frame to show, None without any clips.
+    pub fn current(&self) -> Option<usize> {
+        self.clips
+            .get(self.current)
+            .map(|clip| clip.animation.current())
+    }
+    /// Switches to the clip called `name`, through a transition clip if there's one for it.
+    /// Does nothing if it's already playing or on its way.
+    pub fn play(&mut self, name: &str) {
+        // Nothing to play until the clips are built.
+        if self.clips.is_empty() {
+            return;
+        }
+        let Some(target) = self.find(name) else {
+            error!("No animation clip named {}", name);
+            return;
+        };
+        if self.current == target || self.queued == Some(target) {
+            return;
+        }
+        let from = self.clips[self.current].name.as_str();
+        let via = self
+            .transitions
+            .iter()
+            .find(|transition| {
+                (transition.from == from || transition.from == "*") && transition.to == name
+            })
+            .and_then(|transition| self.find(&transition.via));
+        match via {
+            Some(via) => {
+                self.queued = Some(target);
+                self.enter(via);
+            }
+            None => {
+                self.queued = None;
+                self.enter(target);
+            }
         }
     }
-    pub fn set_current(&mut self, index: usize) {
-        if (0..self.animations.len()).contains(&index) {
-            self.current = index;
-        } else {
-            error!("Invalid index");
+    fn enter(&mut self, index: usize) {
+        self.current = index;
+        if self.clips[index].restart {
+            self.clips[index].animation.reset();
         }
     }
     pub fn tick(&mut self, delta: Duration) {
-        if self.animations.is_empty() {
+        let Some(clip) = self.clips.get_mut(self.current) else {
             return;
+        };
+        clip.animation.tick(delta);
+        if clip.animation.finished() {
+            if let Some(next) = self.queued.take() {
+                self.enter(next);
+            }
         }
-        self.animations[self.current].tick(delta);
     }
     pub fn just_finished(&self) -> bool {
-        if self.animations.is_empty() {
-            return false;
-        };
-        self.animations[self.current].just_finished()
+        self.clips
+            .get(self.current)
+            .is_some_and(|clip| clip.animation.just_finished())
     }
-}
-impl Default for RustAnimationAtlas {
-    fn default() -> Self {
-        Self {
-            animations: vec![],
-            current: 0,
-        }
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        self.clips
+            .get(self.current)
+            .into_iter()
+            .flat_map(|clip| clip.animation.events())
     }
 }
 
+/// Triggered on an entity when its animation moves onto a frame with an event.
+#[derive(Event, Clone, Debug)]
+pub struct AnimationEvent(pub String);
+
 pub fn update_rust_animation(
     time: Res<Time>,
-    mut query: Query<(&mut RustAnimation, &mut TextureAtlas)>,
+    mut query: Query<(Entity, &mut RustAnimation, &mut TextureAtlas)>,
+    mut commands: Commands,
 ) {
-    for (mut animation, mut atlas) in &mut query {
+    for (entity, mut animation, mut atlas) in &mut query {
         animation.tick(time.delta());
         atlas.index = animation.current();
+        for event in animation.events() {
+            commands.trigger_targets(AnimationEvent(event.to_string()), entity);
+        }
     }
 }
 pub fn update_rust_animation_atlas(
     time: Res<Time>,
-    mut query: Query<(&mut RustAnimationAtlas, &mut TextureAtlas)>,
+    mut query: Query<(Entity, &mut RustAnimationAtlas, &mut TextureAtlas)>,
+    mut commands: Commands,
 ) {
-    for (mut animation, mut atlas) in &mut query {
+    for (entity, mut animation, mut atlas) in &mut query {
         animation.tick(time.delta());
-        atlas.index = animation.current();
+        if let Some(index) = animation.current() {
+            atlas.index = index;
+        }
+        for event in animation.events() {
+            commands.trigger_targets(AnimationEvent(event.to_string()), entity);
+        }
     }
 }
 
 #[derive(Component)]
 pub struct RustAnimation {
     animation_type: RustAnimationType,
+    mode: LoopMode,
     time: Duration,
-    just_finished: bool,
+    just_finished: bool,          // moved on a frame this tick
+    finished: bool,               // stopped at the end, never for Loop or PingPong
+    backwards: bool,              // on the way back through a PingPong
+    events: Vec<(usize, String)>, // position in the list, and the event sent on moving onto it
 }
 
 impl RustAnimation {
     pub fn new(animation_type: RustAnimationType) -> Self {
         Self {
             animation_type,
+            mode: LoopMode::Loop,
             time: Duration::default(),
             just_finished: false,
+            finished: false,
+            backwards: false,
+            events: Vec::new(),
         }
     }
     fn variable_timing_list(value: impl Into<Vec<usize>>, timing: impl Into<Vec<f32>>) -> Self {
         Self::new(RustAnimationType::variable_timing_list(value, timing))
     }
-    fn variable_timing_range(start: usize, end: usize, timing: impl Into<Vec<f32>>) -> Self {
+    pub fn variable_timing_range(start: usize, end: usize, timing: impl Into<Vec<f32>>) -> Self {
         Self::new(RustAnimationType::variable_timing_range(start, end, timing))
     }
     pub fn range(start: usize, end: usize, step: f32) -> Self {
@@ -104,31 +222,88 @@ impl RustAnimation {
         Self::new(animation_type)
     }
     pub fn with_timings(self, timing: impl Into<Vec<f32>>) -> Self {
-        match self.animation_type {
-            RustAnimationType::IndexList { indices, .. } => {
-                Self::variable_timing_list(indices, timing)
-            }
-            RustAnimationType::VariableTimingList { indices, .. } => {
-                Self::variable_timing_list(indices, timing)
-            }
+        let indices = match &self.animation_type {
+            RustAnimationType::IndexList { indices, .. } => indices.clone(),
+            RustAnimationType::VariableTimingList { indices, .. } => indices.clone(),
+        };
+        Self {
+            animation_type: RustAnimationType::variable_timing_list(indices, timing),
+            ..self
+        }
+    }
+    pub fn with_mode(self, mode: LoopMode) -> Self {
+        Self { mode, ..self }
+    }
+    pub fn with_events(self, events: impl Into<Vec<(usize, String)>>) -> Self {
+        Self {
+            events: events.into(),
+            ..self
         }
     }
     pub fn tick(&mut self, duration: Duration) {
+        self.just_finished = false;
+        if self.finished {
+            return;
+        }
         self.time += duration;
         if self.time >= self.step() {
             self.time = Duration::default();
-            self.animation_type.next();
+            self.next();
             self.just_finished = true;
-        } else {
-            self.just_finished = false;
         }
     }
+    fn next(&mut self) {
+        let last = self.animation_type.len() - 1;
+        let position = self.animation_type.position_mut();
+        match self.mode {
+            LoopMode::Loop => *position = if *position >= last { 0 } else { *position + 1 },
+            LoopMode::Once | LoopMode::HoldLast if *position >= last => {
+                if self.mode == LoopMode::Once {
+                    *position = 0;
+                }
+                self.finished = true;
+            }
+            LoopMode::Once | LoopMode::HoldLast => *position += 1,
+            LoopMode::PingPong if last == 0 => {}
+            LoopMode::PingPong => {
+                if *position >= last {
+                    self.backwards = true;
+                } else if *position == 0 {
+                    self.backwards = false;
+                }
+                *position = match self.backwards {
+                    true => *position - 1,
+                    false => *position + 1,
+                };
+            }
+        }
+    }
+    /// Back to the first frame, playing again if it had stopped.
+    pub fn reset(&mut self) {
+        *self.animation_type.position_mut() = 0;
+        self.time = Duration::default();
+        self.just_finished = false;
+        self.finished = false;
+        self.backwards = false;
+    }
     pub fn current(&self) -> usize {
         self.animation_type.current()
     }
     pub fn just_finished(&self) -> bool {
         self.just_finished
     }
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+    /// Events on the frame it just moved onto.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        let position = self.animation_type.position();
+        let moved = self.just_finished && !self.finished;
+        self.events
+            .iter()
+            .filter(move |(at, _)| moved && *at == position)
+            .map(|(_, event)| event.as_str())
+    }
     pub fn step(&self) -> Duration {
         match &self.animation_type {
             RustAnimationType::IndexList { step, .. } => *step,
@@ -189,26 +364,22 @@ impl RustAnimationType {
             step: Duration::from_secs_f32(step),
         }
     }
-    pub fn next(&mut self) {
+    fn len(&self) -> usize {
         match self {
-            Self::IndexList {
-                indices, position, ..
-            } => {
-                if *position + 1 >= indices.len() {
-                    *position = 0;
-                } else {
-                    *position += 1
-                }
-            }
-            RustAnimationType::VariableTimingList {
-                indices, position, ..
-            } => {
-                if *position + 1 >= indices.len() {
-                    *position = 0;
-                } else {
-                    *position += 1
-                }
-            }
+            Self::IndexList { indices, .. } => indices.len(),
+            Self::VariableTimingList { indices, .. } => indices.len(),
+        }
+    }
+    fn position(&self) -> usize {
+        match self {
+            Self::IndexList { position, .. } => *position,
+            Self::VariableTimingList { position, .. } => *position,
+        }
+    }
+    fn position_mut(&mut self) -> &mut usize {
+        match self {
+            Self::IndexList { position, .. } => position,
+            Self::VariableTimingList { position, .. } => position,
         }
     }
     pub fn current(&self) -> usize {
@@ -237,42 +408,241 @@ impl RustAnimationType {
     }
 }
 
+/// Sprite sheet and clips for one kind of sprite, from an `.anim.ron` file in assets. Entities with an
+/// `AnimationSource` pointing at it get their atlas built from it, again whenever the file changes.
+#[derive(Asset, TypePath, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnimationSet {
+    pub sheet: SpriteSheet,
+    pub clips: Vec<ClipDef>, // the first one plays to begin with
+    #[serde(default)]
+    pub transitions: Vec<AnimationTransition>,
+}
+impl AnimationSet {
+    pub fn atlas(&self) -> RustAnimationAtlas {
+        let mut atlas = RustAnimationAtlas::new();
+        for clip in &self.clips {
+            let Some(animation) = clip.animation() else {
+                warn!("Animation clip {} has no frames, skipping it.", clip.name);
+                continue;
+            };
+            atlas = atlas.with_clip(clip.name.clone(), animation);
+            if !clip.restart {
+                atlas = atlas.keep_frame();
+            }
+        }
+        atlas.transitions = self.transitions.clone();
+        atlas
+    }
+}
+
+/// Grid of equally sized frames, numbered left to right then down.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpriteSheet {
+    pub image: String, // path under assets
+    pub tile_size: UVec2,
+    pub columns: u32,
+    pub rows: u32,
+    #[serde(default)]
+    pub padding: Option<UVec2>,
+    #[serde(default)]
+    pub offset: Option<UVec2>,
+}
+impl SpriteSheet {
+    pub fn layout(&self) -> TextureAtlasLayout {
+        TextureAtlasLayout::from_grid(
+            self.tile_size,
+            self.columns,
+            self.rows,
+            self.padding,
+            self.offset,
+        )
+    }
+}
+
+/// A clip as written in an animation file. Missing fields use the defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipDef {
+    pub name: String,
+    pub frames: Vec<usize>,  // sheet indices
+    pub duration: f32,       // seconds per frame
+    pub durations: Vec<f32>, // seconds for each frame instead, used when there's one per frame
+    pub mode: LoopMode,
+    pub restart: bool,
+    pub events: Vec<(usize, String)>, // position in `frames`, and the event sent on reaching it
+}
+impl Default for ClipDef {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            frames: Vec::new(),
+            duration: 0.1,
+            durations: Vec::new(),
+            mode: LoopMode::Loop,
+            restart: true,
+            events: Vec::new(),
+        }
+    }
+}
+impl ClipDef {
+    /// None without any frames.
+    pub fn animation(&self) -> Option<RustAnimation> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let animation = match self.durations.len() == self.frames.len() {
+            true => RustAnimation::variable_timing_list(
+                self.frames.clone(),
+                self.durations
+                    .iter()
+                    .map(|d| d.max(0.0))
+                    .collect::<Vec<_>>(),
+            ),
+            false => RustAnimation::list(self.frames.clone(), self.duration.max(0.0)),
+        };
+        Some(
+            animation
+                .with_mode(self.mode)
+                .with_events(self.events.clone()),
+        )
+    }
+}
+
+/// Where an entity's animations, sprite sheet image and layout come from.
+#[derive(Component, Clone, Debug)]
+pub struct AnimationSource {
+    pub set: Handle<AnimationSet>,
+    pub clip: Option<String>, // played once it's built, instead of the first clip
+}
+impl AnimationSource {
+    pub fn new(set: Handle<AnimationSet>) -> Self {
+        Self { set, clip: None }
+    }
+    pub fn playing(self, clip: impl Into<String>) -> Self {
+        Self {
+            clip: Some(clip.into()),
+            ..self
+        }
+    }
+    /// The set's clips ready to play straight away, empty if it hasn't loaded.
+    pub fn atlas(&self, sets: &Assets<AnimationSet>) -> RustAnimationAtlas {
+        let mut atlas = sets
+            .get(&self.set)
+            .map(AnimationSet::atlas)
+            .unwrap_or_default();
+        if let Some(index) = self.clip.as_deref().and_then(|clip| atlas.find(clip)) {
+            atlas.enter(index);
+        }
+        atlas
+    }
+}
+
+/// Builds the atlas for new sources, and for every entity using a set when it's reloaded, carrying on
+/// with the clip that was playing.
+#[allow(clippy::type_complexity)]
+pub fn apply_animation_sets(
+    sets: Res<Assets<AnimationSet>>,
+    mut events: EventReader<AssetEvent<AnimationSet>>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut sheets: Local<HashMap<AssetId<AnimationSet>, (Handle<Image>, Handle<TextureAtlasLayout>)>>,
+    mut sources: Query<(
+        Entity,
+        Ref<AnimationSource>,
+        Option<&RustAnimationAtlas>,
+        Option<&mut TextureAtlas>,
+        Option<&mut Handle<Image>>,
+    )>,
+    mut commands: Commands,
+) {
+    let changed = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    for id in &changed {
+        sheets.remove(id);
+    }
+    for (entity, source, atlas, texture_atlas, image) in sources.iter_mut() {
+        let id = source.set.id();
+        if !source.is_added() && !changed.contains(&id) {
+            continue;
+        }
+        let Some(set) = sets.get(id) else {
+            continue;
+        };
+        let (sheet_image, layout) = sheets
+            .entry(id)
+            .or_insert_with(|| {
+                (
+                    asset_server.load(set.sheet.image.clone()),
+                    layouts.add(set.sheet.layout()),
+                )
+            })
+            .clone();
+        let mut rebuilt = set.atlas();
+        let playing = atlas
+            .and_then(RustAnimationAtlas::current_clip)
+            .map(str::to_string)
+            .or(source.clip.clone());
+        if let Some(index) = playing.and_then(|clip| rebuilt.find(&clip)) {
+            rebuilt.enter(index);
+        }
+        if let Some(mut texture_atlas) = texture_atlas {
+            texture_atlas.layout = layout;
+            texture_atlas.index = rebuilt.current().unwrap_or_default();
+        }
+        if let Some(mut image) = image {
+            *image = sheet_image;
+        }
+        commands.entity(entity).insert(rebuilt);
+    }
+}
+
 pub struct RustAnimationPlugin;
 
 impl Plugin for RustAnimationPlugin {
     fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationSet>();
+        app.register_asset_loader(RonAssetLoader::<AnimationSet>::new(&["anim.ron"]));
         app.add_systems(
             Update,
             (
                 update_rust_animation,
+                apply_animation_sets.before(update_rust_animation_atlas),
                 update_rust_animation_atlas,
-                debug_spline,
             ),
         );
     }
 }
 
-pub fn linear_path<T: Interpolation<T>>(values: &[T], time: f32) -> T {
-    if values.len() < 2 {
-        panic!("Must have at least 2 values");
+/// Evenly spaced stops over `time` 0 to 1, None without any values.
+pub fn linear_path<T: Interpolation<T> + Clone>(values: &[T], time: f32) -> Option<T> {
+    match values {
+        [] => None,
+        [only] => Some(only.clone()),
+        _ => {
+            let steps = values.len() - 1;
+            let position = time.clamp(0.0, 1.0) * steps as f32;
+            // Time 1 lands at the start of a step past the end, finish the last one instead.
+            let index = (position.floor() as usize).min(steps - 1);
+            Some(values[index].linear(&values[index + 1], position - index as f32))
+        }
     }
-    let steps = values.len() as f32 - 1.0;
-    let step_size = 1.0 / steps;
-    let index = (time / step_size).floor() as usize;
-    let start_time = index as f32 * step_size;
-    let current_time = time - start_time;
-    let t = current_time / step_size;
-    values[index].linear(&values[index + 1], t)
 }
 
+/// Blending from `self` to `next`. Outside of 0 to 1 it carries on past the ends, for easings that
+/// overshoot.
 pub trait Interpolation<T> {
     fn linear(&self, next: &T, time: f32) -> T;
 }
 impl Interpolation<f32> for f32 {
     fn linear(&self, next: &f32, time: f32) -> f32 {
         match time {
-            time if time <= 0.0 => self.to_owned(),
-            time if time >= 1.0 => next.to_owned(),
+            0.0 => self.to_owned(),
+            1.0 => next.to_owned(),
             time => (1.0 - time) * self + time * next,
         }
     }
@@ -282,65 +652,232 @@ impl Interpolation<Vec2> for Vec2 {
         Vec2::new(self.x.linear(&next.x, time), self.y.linear(&next.y, time))
     }
 }
+impl Interpolation<Vec3> for Vec3 {
+    fn linear(&self, next: &Vec3, time: f32) -> Vec3 {
+        Vec3::new(
+            self.x.linear(&next.x, time),
+            self.y.linear(&next.y, time),
+            self.z.linear(&next.z, time),
+        )
+    }
+}
+impl Interpolation<Quat> for Quat {
+    fn linear(&self, next: &Quat, time: f32) -> Quat {
+        self.slerp(*next, time)
+    }
+}
+impl Interpolation<Color> for Color {
+    /// Mixed in linear space, so halfway between two colors isn't darker than either.
+    fn linear(&self, next: &Color, time: f32) -> Color {
+        let (from, to) = (self.to_linear(), next.to_linear());
+        LinearRgba::new(
+            from.red.linear(&to.red, time),
+            from.green.linear(&to.green, time),
+            from.blue.linear(&to.blue, time),
+            from.alpha.linear(&to.alpha, time),
+        )
+        .into()
+    }
+}
+impl Interpolation<Transform> for Transform {
+    fn linear(&self, next: &Transform, time: f32) -> Transform {
+        Transform {
+            translation: self.translation.linear(&next.translation, time),
+            rotation: self.rotation.linear(&next.rotation, time),
+            scale: self.scale.linear(&next.scale, time),
+        }
+    }
+}
 
-#[derive(Component)]
-pub struct Spline(CubicCardinalSpline<Vec2>);
-
-pub fn debug_spline(
-    mut splines: Query<(&mut Spline, &Transform)>,
-    mut gizmos: Gizmos,
-    mouse_button: Res<ButtonInput<MouseButton>>,
-    position: Res<MousePosition>,
-) {
-    for (mut spline, transform) in splines.iter_mut() {
-        for mut p in spline.0.control_points.iter_mut() {
-            if mouse_button.pressed(MouseButton::Left) {
-                if p.distance(position.0 - transform.translation.xy()) < 2.0 {
-                    *p = position.0 - transform.translation.xy();
-                    break;
-                }
-            } else {
+/// Shapes progress through a motion, `t` runs 0 to 1 and so does the result, though Back and Elastic
+/// overshoot on the way.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+impl Easing {
+    pub const ALL: [Easing; 19] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::SineIn,
+        Easing::SineOut,
+        Easing::SineInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+    ];
+    pub fn ease(self, t: f32) -> f32 {
+        use Easing::*;
+        let t = t.clamp(0.0, 1.0);
+        // Every family is written as its In curve, Out runs it backwards and InOut does half of each.
+        let ease_in: fn(f32) -> f32 = match self {
+            Linear => return t,
+            QuadIn | QuadOut | QuadInOut => |t| t * t,
+            CubicIn | CubicOut | CubicInOut => |t| t * t * t,
+            SineIn | SineOut | SineInOut => |t| 1.0 - (t * f32::FRAC_PI_2()).cos(),
+            BackIn | BackOut | BackInOut => |t| {
+                const BACK: f32 = 1.70158;
+                t * t * ((BACK + 1.0) * t - BACK)
+            },
+            ElasticIn | ElasticOut | ElasticInOut => |t| match t {
+                0.0 | 1.0 => t,
+                t => -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * f32::TAU() / 3.0).sin(),
+            },
+            BounceIn | BounceOut | BounceInOut => |t| 1.0 - bounce_out(1.0 - t),
+        };
+        match self {
+            QuadIn | CubicIn | SineIn | BackIn | ElasticIn | BounceIn => ease_in(t),
+            QuadOut | CubicOut | SineOut | BackOut | ElasticOut | BounceOut => {
+                1.0 - ease_in(1.0 - t)
             }
+            _ if t < 0.5 => ease_in(2.0 * t) / 2.0,
+            _ => 1.0 - ease_in(2.0 - 2.0 * t) / 2.0,
         }
-        for mut p in spline.0.control_points.iter() {
-            gizmos.circle_2d(
-                transform.translation.xy() + *p,
-                1.0,
-                Color::srgb(1.0, 0.0, 0.0),
-            );
-        }
-        let line_points = spline
-            .0
-            .to_curve()
-            .iter_positions(100)
-            .map(|p| p + transform.translation.xy())
-            .collect::<Vec<_>>();
+    }
+    /// Parses the names used in LDtk fields, anything unknown is linear.
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|easing| format!("{:?}", easing) == name)
+            .unwrap_or_default()
+    }
+}
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    match t {
+        t if t < 1.0 / D => N * t * t,
+        t if t < 2.0 / D => N * (t - 1.5 / D).powi(2) + 0.75,
+        t if t < 2.5 / D => N * (t - 2.25 / D).powi(2) + 0.9375,
+        t => N * (t - 2.625 / D).powi(2) + 0.984375,
+    }
+}
 
-        gizmos.linestrip_2d(line_points.clone(), Color::srgb(0.0, 0.0, 1.0));
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplineKind {
+    #[default]
+    CatmullRom, // through every point
+    BSpline, // smoother, pulled towards the points but only through the ends
+    Bezier,  // through every third point, the two between are handles
+}
+impl SplineKind {
+    pub const ALL: [SplineKind; 3] = [
+        SplineKind::CatmullRom,
+        SplineKind::BSpline,
+        SplineKind::Bezier,
+    ];
+}
 
-        let line_gradients = spline
-            .0
-            .to_curve()
-            .iter_velocities(100)
-            .map(|a| a.to_angle() + f32::FRAC_PI_2())
+/// Control points of a path, relative to whatever the path belongs to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Spline {
+    pub points: Vec<Vec2>,
+    pub kind: SplineKind,
+}
+impl Spline {
+    pub fn new(points: impl Into<Vec<Vec2>>, kind: SplineKind) -> Self {
+        Self {
+            points: points.into(),
+            kind,
+        }
+    }
+    /// The curve starts at the first point, `closed` carries on from the last point back round to it.
+    pub fn curve(&self, closed: bool) -> CubicCurve<Vec2> {
+        let points = &self.points;
+        let count = points.len();
+        if count < 2 {
+            return CubicCurve { segments: vec![] };
+        }
+        let (first, last) = (points[0], points[count - 1]);
+        match self.kind {
+            SplineKind::CatmullRom if closed => {
+                // Wrap the ends around so the curve stays smooth through the start, then drop the
+                // lead-in and lead-out segments.
+                let wrapped = [&[last], &points[..], &[first, points[1]]].concat();
+                let mut curve = CubicCardinalSpline::new_catmull_rom(wrapped).to_curve();
+                curve.segments = curve.segments[1..=count].to_vec();
+                curve
+            }
+            SplineKind::CatmullRom => {
+                CubicCardinalSpline::new_catmull_rom(points.clone()).to_curve()
+            }
+            // A B-spline only passes near its points, shift the loop over so it starts on the first
+            // one like the other kinds do.
+            SplineKind::BSpline if closed => {
+                let start = (last + first * 4.0 + points[1]) / 6.0;
+                let wrapped = [&[last], &points[..], &[first, points[1]]].concat();
+                let shifted = wrapped.iter().map(|point| *point + first - start);
+                CubicBSpline::new(shifted.collect::<Vec<_>>()).to_curve()
+            }
+            // Tripling the ends pins the curve to them.
+            SplineKind::BSpline => {
+                CubicBSpline::new([&[first, first], &points[..], &[last, last]].concat()).to_curve()
+            }
+            SplineKind::Bezier => {
+                let points = match closed {
+                    true => [&points[..], &[first]].concat(),
+                    false => points.clone(),
+                };
+                let mut segments = points
+                    .windows(4)
+                    .step_by(3)
+                    .map(|p| [p[0], p[1], p[2], p[3]])
+                    .collect::<Vec<_>>();
+                // Anything left after the last full segment bends a short one on the end.
+                let anchor = segments.len() * 3;
+                match &points[anchor..] {
+                    [a, b] => segments.push([*a, *a, *b, *b]),
+                    [a, handle, b] => segments.push([*a, *handle, *handle, *b]),
+                    _ => {}
+                }
+                CubicBezier::new(segments).to_curve()
+            }
+        }
+    }
+    /// Length along the curve, measured over straight pieces.
+    pub fn arc_length(&self, closed: bool) -> f32 {
+        let curve = self.curve(closed);
+        if curve.segments.is_empty() {
+            return 0.0;
+        }
+        let positions = curve
+            .iter_positions(curve.segments.len() * 32)
             .collect::<Vec<_>>();
-
-        line_points.iter().zip(line_gradients).for_each(|(p, g)| {
-            gizmos.line_2d(
-                *p,
-                *p + vec2(g.cos(), g.sin()) * 3.0,
-                Color::srgb(0.0, 1.0, 0.0),
-            );
-            gizmos.line_2d(
-                *p,
-                *p - vec2(g.cos(), g.sin()) * 3.0,
-                Color::srgb(0.0, 1.0, 0.0),
-            );
-        });
+        positions.windows(2).map(|p| p[0].distance(p[1])).sum()
     }
 }
 
-pub fn circle_spline() -> CubicCardinalSpline<Vec2> {
+pub fn circle_spline() -> Spline {
     let points = (0..360)
         .step_by(40)
         .map(|x| {
@@ -351,5 +888,161 @@ pub fn circle_spline() -> CubicCardinalSpline<Vec2> {
             Vec2::new(xpos, ypos)
         })
         .collect::<Vec<_>>();
-    CubicCardinalSpline::new_catmull_rom(points)
+    Spline::new(points, SplineKind::CatmullRom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    fn frames(animation: &mut RustAnimation, ticks: usize) -> Vec<usize> {
+        (0..ticks)
+            .map(|_| {
+                animation.tick(Duration::from_secs_f32(0.1));
+                animation.current()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_modes_after_the_last_frame() {
+        let clip = |mode| RustAnimation::list([0, 1, 2], 0.1).with_mode(mode);
+        assert_eq!(frames(&mut clip(LoopMode::Loop), 5), [1, 2, 0, 1, 2]);
+        assert_eq!(frames(&mut clip(LoopMode::PingPong), 6), [1, 2, 1, 0, 1, 2]);
+        assert_eq!(frames(&mut clip(LoopMode::HoldLast), 5), [1, 2, 2, 2, 2]);
+        let mut once = clip(LoopMode::Once);
+        assert_eq!(frames(&mut once, 5), [1, 2, 0, 0, 0]);
+        assert!(once.finished());
+        once.reset();
+        assert_eq!(frames(&mut once, 1), [1]);
+    }
+
+    #[test]
+    fn atlas_plays_transitions_and_restarts_clips() {
+        let mut atlas = RustAnimationAtlas::new()
+            .with_clip("idle", RustAnimation::list([0], 0.1))
+            .with_clip("walk", RustAnimation::list([1, 2, 3], 0.1))
+            .with_clip(
+                "land",
+                RustAnimation::list([9], 0.1).with_mode(LoopMode::Once),
+            )
+            .with_transition("*", "idle", "land");
+        atlas.play("walk");
+        atlas.tick(Duration::from_secs_f32(0.1));
+        assert_eq!(atlas.current(), Some(2));
+        atlas.play("walk");
+        assert_eq!(atlas.current(), Some(2));
+        atlas.play("idle");
+        assert_eq!(atlas.current_clip(), Some("land"));
+        atlas.play("idle");
+        atlas.tick(Duration::from_secs_f32(0.1));
+        assert_eq!(atlas.current_clip(), Some("idle"));
+        atlas.play("walk");
+        assert_eq!(atlas.current(), Some(1));
+    }
+
+    #[test]
+    fn sources_build_their_atlas_once_loaded() {
+        let mut sets = Assets::<AnimationSet>::default();
+        let unloaded = AnimationSource::new(Handle::default());
+        let mut atlas = unloaded.atlas(&sets);
+        atlas.play("Idle");
+        assert_eq!(atlas.current(), None);
+
+        let set: AnimationSet =
+            ron::de::from_str(include_str!("../assets/flags.anim.ron")).unwrap();
+        let source = AnimationSource::new(sets.add(set)).playing("Finish");
+        assert_eq!(source.atlas(&sets).current_clip(), Some("Finish"));
+    }
+
+    #[test]
+    fn shipped_animations_parse() {
+        for file in [
+            include_str!("../assets/player.anim.ron"),
+            include_str!("../assets/flags.anim.ron"),
+        ] {
+            let set: AnimationSet = ron::de::from_str(file).unwrap();
+            let mut atlas = set.atlas();
+            assert_eq!(atlas.clips.len(), set.clips.len());
+            for transition in &set.transitions {
+                assert!(atlas.find(&transition.via).is_some());
+            }
+            atlas.play(&set.clips.last().unwrap().name);
+        }
+    }
+
+    #[test]
+    fn events_fire_on_reaching_their_frame() {
+        let clip = ClipDef {
+            frames: vec![0, 1, 2],
+            events: vec![(1, "step".to_string())],
+            mode: LoopMode::HoldLast,
+            ..default()
+        };
+        let mut animation = clip.animation().unwrap();
+        let mut fired = vec![];
+        for _ in 0..5 {
+            animation.tick(Duration::from_secs_f32(0.1));
+            fired.push(animation.events().count());
+        }
+        assert_eq!(fired, [1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn linear_path_handles_the_ends() {
+        assert_eq!(linear_path::<f32>(&[], 0.5), None);
+        assert_eq!(linear_path(&[3.0], 0.5), Some(3.0));
+        let values = [0.0, 10.0, 30.0];
+        assert_eq!(linear_path(&values, 0.0), Some(0.0));
+        assert_eq!(linear_path(&values, 0.75), Some(20.0));
+        assert_eq!(linear_path(&values, 1.0), Some(30.0));
+        assert_eq!(linear_path(&values, 2.0), Some(30.0));
+    }
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        for easing in Easing::ALL {
+            assert!(easing.ease(0.0).abs() < 1e-5, "{:?}", easing);
+            assert!((easing.ease(1.0) - 1.0).abs() < 1e-5, "{:?}", easing);
+            assert_eq!(Easing::from_name(&format!("{:?}", easing)), easing);
+        }
+    }
+
+    #[test]
+    fn every_kind_starts_and_ends_on_the_path_ends() {
+        let points = [
+            Vec2::ZERO,
+            vec2(16.0, 32.0),
+            vec2(48.0, 32.0),
+            vec2(64.0, 0.0),
+        ];
+        for kind in SplineKind::ALL {
+            let curve = Spline::new(points, kind).curve(false);
+            let end = curve.segments.len() as f32;
+            assert!(curve.position(0.0).distance(points[0]) < 1e-3, "{:?}", kind);
+            assert!(curve.position(end).distance(points[3]) < 1e-3, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn closed_paths_loop_through_the_first_point() {
+        let points = [Vec2::ZERO, vec2(32.0, 0.0), vec2(32.0, 32.0)];
+        for kind in SplineKind::ALL {
+            let curve = Spline::new(points, kind).curve(true);
+            let end = curve.segments.len() as f32;
+            assert!(curve.position(0.0).distance(points[0]) < 1e-3, "{:?}", kind);
+            assert!(curve.position(end).distance(points[0]) < 1e-3, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn arc_length_of_a_straight_path() {
+        let path = Spline::new(
+            [Vec2::ZERO, vec2(16.0, 0.0), vec2(32.0, 0.0)],
+            SplineKind::CatmullRom,
+        );
+        assert!((path.arc_length(false) - 32.0).abs() < 1e-2);
+        assert!(path.arc_length(true) > 64.0);
+    }
 }
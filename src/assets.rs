@@ -1,3 +1,6 @@
+use crate::animation::AnimationSet;
+use crate::entities::platform::SplinePaths;
+use crate::movement_profile::MovementProfiles;
 use crate::GameState;
 use bevy::asset::Handle;
 use bevy::prelude::*;
@@ -10,9 +13,11 @@ impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
         app.add_loading_state(
             LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::LoadGame)
+                .continue_to_state(GameState::Menu)
                 .load_collection::<Sounds>()
-                .load_collection::<Levels>(),
+                .load_collection::<Levels>()
+                .load_collection::<Tuning>()
+                .load_collection::<Animations>(),
         );
     }
 }
@@ -36,4 +41,20 @@ pub struct Sounds {
 pub struct Levels {
     #[asset(path = "run_level.ldtk")]
     pub level1: Handle<LdtkProject>,
+    #[asset(path = "platform.paths.ron")]
+    pub paths: Handle<SplinePaths>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct Tuning {
+    #[asset(path = "movement.profiles.ron")]
+    pub movement: Handle<MovementProfiles>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct Animations {
+    #[asset(path = "player.anim.ron")]
+    pub player: Handle<AnimationSet>,
+    #[asset(path = "flags.anim.ron")]
+    pub flags: Handle<AnimationSet>,
 }
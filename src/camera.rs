@@ -1,5 +1,8 @@
+use crate::animation::Spline;
+use crate::entities::camera_rail::CameraRail;
 use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct MainCamera;
@@ -7,6 +10,22 @@ pub struct MainCamera;
 #[derive(Component, Default)]
 pub struct Follow;
 
+/// How loosely the camera trails the player, part of the movement profile.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraFollow {
+    pub dead_zone: Vec2, // pixels the player can move before the camera starts following
+    pub speed: f32,      // fraction of the distance caught up per second
+}
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::new(16.0, 48.0),
+            speed: 3.0,
+        }
+    }
+}
+
 pub fn setup_camera(mut commands: Commands) {
     commands.spawn((
         MainCamera,
@@ -24,17 +43,28 @@ pub fn setup_camera(mut commands: Commands) {
 pub fn move_camera(
     mut cam_query: Query<&mut Transform, With<MainCamera>>,
     follow: Query<&Transform, (With<Follow>, Without<MainCamera>)>,
+    rails: Query<(&CameraRail, &Spline, &GlobalTransform)>,
+    camera_follow: Res<CameraFollow>,
     time: Res<Time>,
 ) {
     if let Ok(mut transform) = cam_query.get_single_mut() {
         if let Ok(fol) = follow.get_single() {
-            let dir = transform.translation.x - fol.translation.x;
-            if dir.abs() > 16.0 {
-                transform.translation.x -= dir * time.delta_seconds() * 3.0;
+            // Near a rail the camera heads for the closest point on it instead.
+            let following = fol.translation.truncate();
+            let target = rails
+                .iter()
+                .filter_map(|(rail, path, origin)| {
+                    rail.focus(path, origin.translation().truncate(), following)
+                })
+                .min_by(|a, b| a.distance(following).total_cmp(&b.distance(following)))
+                .unwrap_or(following);
+            let dir = transform.translation.x - target.x;
+            if dir.abs() > camera_follow.dead_zone.x {
+                transform.translation.x -= dir * time.delta_seconds() * camera_follow.speed;
             }
-            let dir = transform.translation.y - fol.translation.y;
-            if dir.abs() > 48.0 {
-                transform.translation.y -= dir * time.delta_seconds() * 3.0;
+            let dir = transform.translation.y - target.y;
+            if dir.abs() > camera_follow.dead_zone.y {
+                transform.translation.y -= dir * time.delta_seconds() * camera_follow.speed;
             }
         }
     }
@@ -43,6 +73,7 @@ pub fn move_camera(
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFollow>();
         app.add_systems(Startup, setup_camera);
         app.add_systems(PostUpdate, move_camera);
     }
@@ -0,0 +1,103 @@
+use crate::entities::player::PlayerMarker;
+use crate::events_systems::RespawnPlayer;
+use crate::level_loader::{level_iid_from_selection, LevelStart};
+use crate::player_movement::{update_character_position_from_velocity, Jump, Run};
+use crate::pushing::update_push_grab;
+use crate::{GameState, PlayerText};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::{LevelIid, LevelSelection};
+
+pub struct DeathPlugin;
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.observe(player_died);
+        app.add_systems(
+            FixedUpdate,
+            tick_dying
+                .after(update_push_grab)
+                .before(update_character_position_from_velocity)
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.add_systems(Update, animate_dying.run_if(in_state(GameState::InGame)));
+    }
+}
+
+/// How long the player lies there before respawning.
+const DEATH_TIME: f32 = 0.75;
+
+#[derive(Event)]
+pub struct PlayerDied {
+    pub reset_race: bool,
+}
+
+/// On the player between dying and respawning, input is ignored meanwhile.
+#[derive(Component)]
+pub struct Dying {
+    timer: Timer,
+    reset_race: bool,
+}
+
+pub fn player_died(
+    trigger: Trigger<PlayerDied>,
+    player: Query<Entity, (With<PlayerMarker>, Without<Dying>)>,
+    mut text_query: Query<&mut Text, With<PlayerText>>,
+    mut commands: Commands,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    commands.entity(player).insert(Dying {
+        timer: Timer::from_seconds(DEATH_TIME, TimerMode::Once),
+        reset_race: trigger.event().reset_race,
+    });
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = "Ouch!".into();
+    }
+}
+
+pub fn tick_dying(
+    mut player: Query<(Entity, &mut Dying, &mut Jump, &mut Run), With<PlayerMarker>>,
+    time: Res<Time>,
+    level_selection: Res<LevelSelection>,
+    mut level_start: ResMut<LevelStart>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut commands: Commands,
+) {
+    let Ok((player, mut dying, mut jump, mut run)) = player.get_single_mut() else {
+        return;
+    };
+    // Hold still where we died.
+    jump.stop();
+    run.stop();
+    if !dying.timer.tick(time.delta()).finished() {
+        return;
+    }
+    commands.entity(player).remove::<Dying>();
+    let level_iid =
+        level_iid_from_selection(&level_selection, &ldtk_projects, &ldtk_project_assets);
+    match level_iid {
+        // Back to the start flag, which also clears the race.
+        Some(level_iid) if dying.reset_race => level_start.0 = Some(LevelIid::new(level_iid)),
+        _ => commands.trigger(RespawnPlayer),
+    }
+}
+
+/// Upside down and fading out, then back to normal once respawned.
+pub fn animate_dying(
+    mut dying: Query<(&Dying, &mut Sprite), With<PlayerMarker>>,
+    mut sprites: Query<&mut Sprite, (With<PlayerMarker>, Without<Dying>)>,
+    mut respawned: RemovedComponents<Dying>,
+) {
+    for (dying, mut sprite) in dying.iter_mut() {
+        sprite.flip_y = true;
+        sprite.color = Color::srgba(1.0, 0.3, 0.3, 1.0 - dying.timer.fraction());
+    }
+    for entity in respawned.read() {
+        if let Ok(mut sprite) = sprites.get_mut(entity) {
+            sprite.flip_y = false;
+            sprite.color = Color::WHITE;
+        }
+    }
+}
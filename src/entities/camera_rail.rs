@@ -0,0 +1,69 @@
+use crate::animation::Spline;
+use crate::entities::platform::path_from_instance;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use bevy_ecs_ldtk::{EntityInstance, LdtkEntity};
+
+/// Holds the camera to the `Spline` on the same entity while the player is close to it.
+#[derive(Component)]
+pub struct CameraRail {
+    pub range: f32, // pixels from the path the player can be for it to take over the camera
+}
+impl Default for CameraRail {
+    fn default() -> Self {
+        Self { range: 96.0 }
+    }
+}
+impl CameraRail {
+    /// Closest point on `path`, drawn from `origin`, to `target`, if it's within range.
+    pub fn focus(&self, path: &Spline, origin: Vec2, target: Vec2) -> Option<Vec2> {
+        let curve = path.curve(false);
+        if curve.segments.is_empty() {
+            return None;
+        }
+        curve
+            .iter_positions(curve.segments.len() * 32)
+            .map(|position| origin + position)
+            .min_by(|a, b| a.distance(target).total_cmp(&b.distance(target)))
+            .filter(|closest| closest.distance(target) <= self.range)
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct CameraRailBundle {
+    #[with(rail_from_instance)]
+    rail: CameraRail,
+    #[with(path_from_instance)]
+    path: Spline,
+}
+fn rail_from_instance(entity_instance: &EntityInstance) -> CameraRail {
+    CameraRail {
+        range: entity_instance
+            .get_float_field("range")
+            .copied()
+            .unwrap_or(CameraRail::default().range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::SplineKind;
+    use bevy::math::vec2;
+
+    #[test]
+    fn focus_follows_the_rail_within_range() {
+        let path = Spline::new(
+            [Vec2::ZERO, vec2(64.0, 0.0), vec2(128.0, 0.0)],
+            SplineKind::CatmullRom,
+        );
+        let rail = CameraRail { range: 32.0 };
+        let origin = vec2(100.0, 50.0);
+        let focus = rail.focus(&path, origin, vec2(164.0, 70.0)).unwrap();
+        assert!(focus.distance(vec2(164.0, 50.0)) < 1.0);
+        assert!(rail.focus(&path, origin, vec2(164.0, 90.0)).is_none());
+        // Past the end it holds on the last point.
+        let focus = rail.focus(&path, origin, vec2(240.0, 50.0)).unwrap();
+        assert!(focus.distance(vec2(228.0, 50.0)) < 1e-3);
+    }
+}
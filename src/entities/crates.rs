@@ -1,14 +1,20 @@
-use bevy::prelude::{Bundle, SpriteBundle};
+use bevy::prelude::{Bundle, Component, SpriteBundle};
 use bevy_ecs_ldtk::LdtkEntity;
-use bevy_rapier2d::dynamics::RigidBody;
-use bevy_rapier2d::geometry::Collider;
+use bevy_rapier2d::dynamics::{ReadMassProperties, RigidBody};
+use bevy_rapier2d::geometry::{Collider, CollisionGroups};
 
+/// Something the player can push, or grab and pull.
+#[derive(Component, Default)]
+pub struct CrateMarker;
 #[derive(Bundle, LdtkEntity)]
 pub struct Crate {
     #[sprite_bundle]
     sprite_bundle: SpriteBundle,
+    crate_marker: CrateMarker,
     rigid_body: RigidBody,
     collider: Collider,
+    collision_groups: CollisionGroups,
+    mass: ReadMassProperties,
 }
 impl Default for Crate {
     fn default() -> Self {
@@ -16,6 +22,9 @@ impl Default for Crate {
             collider: Collider::cuboid(8.0, 8.0),
             rigid_body: RigidBody::Dynamic,
             sprite_bundle: Default::default(),
+            crate_marker: CrateMarker,
+            collision_groups: CollisionGroups::default(),
+            mass: Default::default(),
         }
     }
 }
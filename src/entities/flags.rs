@@ -1,5 +1,6 @@
-use crate::animation::RustAnimation;
-use crate::{Finish, Start};
+use crate::animation::AnimationSource;
+use crate::assets::Animations;
+use crate::{Checkpoint, Finish, Start};
 use bevy::math::vec2;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::{LdtkEntity, LdtkSpriteSheetBundle};
@@ -36,22 +37,45 @@ pub struct FinishFlag {
     sprite_bundle: LdtkSpriteSheetBundle,
     flag_bundle: FlagBundle,
 }
+/// Green flag tinted yellow, so it doesn't look like another start.
+pub const CHECKPOINT_COLOR: Color = Color::srgb(1.0, 0.8, 0.0);
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct CheckpointFlag {
+    checkpoint: Checkpoint,
+    #[sprite_sheet_bundle("flag_red_green.png", 125, 250, 4, 2, 0, 0, 4)]
+    sprite_bundle: LdtkSpriteSheetBundle,
+    flag_bundle: FlagBundle,
+}
 
+#[allow(clippy::type_complexity)]
 pub fn spawn_flags(
     mut start: Query<(Entity, &mut Sprite), (Added<Start>, Without<Finish>)>,
     mut finish: Query<(Entity, &mut Sprite), (Added<Finish>, Without<Start>)>,
+    mut checkpoint: Query<
+        (Entity, &mut Sprite),
+        (Added<Checkpoint>, Without<Start>, Without<Finish>),
+    >,
+    animations: Res<Animations>,
     mut commands: Commands,
 ) {
+    let source = AnimationSource::new(animations.flags.clone());
     for (entity, mut sprite) in start.iter_mut() {
         sprite.custom_size = Some(vec2(16.0, 32.0));
         commands
             .entity(entity)
-            .insert(RustAnimation::range(4, 7, 0.1));
+            .insert(source.clone().playing("Start"));
     }
     for (entity, mut sprite) in finish.iter_mut() {
         sprite.custom_size = Some(vec2(16.0, 32.0));
         commands
             .entity(entity)
-            .insert(RustAnimation::range(0, 3, 0.1));
+            .insert(source.clone().playing("Finish"));
+    }
+    for (entity, mut sprite) in checkpoint.iter_mut() {
+        sprite.custom_size = Some(vec2(16.0, 32.0));
+        sprite.color = CHECKPOINT_COLOR;
+        commands
+            .entity(entity)
+            .insert(source.clone().playing("Checkpoint"));
     }
 }
@@ -0,0 +1,74 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use bevy_ecs_ldtk::{EntityInstance, LdtkEntity};
+use bevy_rapier2d::geometry::{ActiveCollisionTypes, ActiveEvents, Collider, Sensor};
+
+/// Touching this kills the player.
+#[derive(Component, Default, Clone, Copy)]
+pub struct Hazard {
+    pub reset_race: bool, // throw away the race and go back to the start flag
+}
+#[derive(Bundle, LdtkEntity)]
+pub struct HazardBundle {
+    #[with(hazard_from_instance)]
+    hazard: Hazard,
+    #[with(hazard_sprite)]
+    sprite_bundle: SpriteBundle,
+    #[with(hazard_collider)]
+    collider: Collider,
+    sensor_bundle: HazardSensorBundle,
+}
+impl Default for HazardBundle {
+    fn default() -> Self {
+        Self {
+            hazard: Default::default(),
+            sprite_bundle: Default::default(),
+            collider: Collider::cuboid(8.0, 8.0),
+            sensor_bundle: Default::default(),
+        }
+    }
+}
+/// Sensor part shared by hazard entities and hazard tiles.
+#[derive(Bundle)]
+pub struct HazardSensorBundle {
+    sensor: Sensor,
+    active_collision_types: ActiveCollisionTypes,
+    active_events: ActiveEvents,
+}
+impl Default for HazardSensorBundle {
+    fn default() -> Self {
+        Self {
+            sensor: Sensor,
+            active_collision_types: ActiveCollisionTypes::KINEMATIC_STATIC,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+        }
+    }
+}
+fn hazard_from_instance(entity_instance: &EntityInstance) -> Hazard {
+    Hazard {
+        reset_race: entity_instance
+            .get_bool_field("reset_race")
+            .copied()
+            .unwrap_or(false),
+    }
+}
+fn hazard_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::srgba(0.9, 0.2, 0.25, 0.6),
+            custom_size: Some(vec2(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            )),
+            ..default()
+        },
+        ..default()
+    }
+}
+fn hazard_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        entity_instance.width as f32 / 2.0,
+        entity_instance.height as f32 / 2.0,
+    )
+}
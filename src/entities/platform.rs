@@ -0,0 +1,235 @@
+use crate::animation::{Easing, Spline, SplineKind};
+use crate::assets::Levels;
+use crate::RaceTime;
+use bevy::math::cubic_splines::CubicCurve;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use bevy_ecs_ldtk::{EntityIid, EntityInstance, LdtkEntity};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Size of the LDtk grid the path points are placed on.
+const TILE_SIZE: f32 = 16.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PathMode {
+    #[default]
+    PingPong, // there and back along the same path
+    Loop, // back to the start from the last point, round and round
+}
+
+/// A kinematic platform following the `Spline` on the same entity.
+#[derive(Component)]
+pub struct MovingPlatform {
+    pub mode: PathMode,
+    pub easing: Easing,   // applied to each pass, or each lap when looping
+    pub duration: f32,    // seconds for one pass or lap
+    time: f32,            // seconds along the path, the race time while one is running
+    origin: Option<Vec2>, // set on the first tick, from the spawned transform
+    pub delta: Vec2,      // how far it moved this tick, for carrying the player
+}
+impl Default for MovingPlatform {
+    fn default() -> Self {
+        Self::new(PathMode::default(), Easing::default(), 4.0)
+    }
+}
+impl MovingPlatform {
+    pub fn new(mode: PathMode, easing: Easing, duration: f32) -> Self {
+        Self {
+            mode,
+            easing,
+            duration: duration.max(0.1),
+            time: 0.0,
+            origin: None,
+            delta: Vec2::ZERO,
+        }
+    }
+    /// Where the path is drawn from, the platform's starting position.
+    pub fn origin(&self) -> Option<Vec2> {
+        self.origin
+    }
+    /// Offset from the start along `curve` after `time` seconds.
+    pub fn offset_at(&self, curve: &CubicCurve<Vec2>, time: f32) -> Vec2 {
+        if curve.segments.is_empty() {
+            return Vec2::ZERO;
+        }
+        let passes = time / self.duration;
+        let t = match self.mode {
+            PathMode::PingPong => 1.0 - ((passes % 2.0) - 1.0).abs(),
+            PathMode::Loop => passes.fract(),
+        };
+        curve.position(self.easing.ease(t) * curve.segments.len() as f32)
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct MovingPlatformBundle {
+    #[with(platform_from_instance)]
+    platform: MovingPlatform,
+    #[with(path_from_instance)]
+    path: Spline,
+    #[with(platform_sprite)]
+    sprite_bundle: SpriteBundle,
+    #[with(platform_collider)]
+    collider: Collider,
+    rigid_body: RigidBody,
+}
+impl Default for MovingPlatformBundle {
+    fn default() -> Self {
+        Self {
+            platform: Default::default(),
+            path: Default::default(),
+            sprite_bundle: Default::default(),
+            collider: Collider::cuboid(8.0, 8.0),
+            rigid_body: RigidBody::KinematicPositionBased,
+        }
+    }
+}
+fn platform_from_instance(entity_instance: &EntityInstance) -> MovingPlatform {
+    let mode = match entity_instance.get_maybe_string_field("mode") {
+        Ok(Some(mode)) if mode == "Loop" => PathMode::Loop,
+        _ => PathMode::PingPong,
+    };
+    let easing = match entity_instance.get_maybe_string_field("easing") {
+        Ok(Some(easing)) => Easing::from_name(easing),
+        _ => Easing::default(),
+    };
+    let duration = entity_instance
+        .get_float_field("duration")
+        .copied()
+        .unwrap_or(4.0);
+    MovingPlatform::new(mode, easing, duration)
+}
+/// Offsets from the start through the `path` points. Saved paths from the editor replace this.
+pub fn path_from_instance(entity_instance: &EntityInstance) -> Spline {
+    // Points mark where the platform's top left tile goes, LDtk's y points down.
+    let start = entity_instance.grid;
+    let points = std::iter::once(Vec2::ZERO)
+        .chain(
+            entity_instance
+                .get_maybe_points_field("path")
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|point| (*point - start).as_vec2() * vec2(1.0, -1.0) * TILE_SIZE),
+        )
+        .collect::<Vec<_>>();
+    Spline::new(points, SplineKind::CatmullRom)
+}
+fn platform_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::srgb(0.45, 0.35, 0.3),
+            custom_size: Some(vec2(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            )),
+            ..default()
+        },
+        ..default()
+    }
+}
+fn platform_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        entity_instance.width as f32 / 2.0,
+        entity_instance.height as f32 / 2.0,
+    )
+}
+
+/// Runs before the player moves, so anyone standing on a platform can be carried along with it.
+pub fn move_platforms(
+    mut platforms: Query<(&mut MovingPlatform, &Spline, &mut Transform)>,
+    race_time: Query<&RaceTime>,
+    time: Res<Time>,
+) {
+    for (mut platform, path, mut transform) in platforms.iter_mut() {
+        let origin = *platform
+            .origin
+            .get_or_insert(transform.translation.truncate());
+        // Keep time with the race while there is one, so every attempt and replay of it sees
+        // the platforms in the same place, even ones spawned partway through.
+        platform.time = match race_time.get_single() {
+            Ok(race_time) => race_time.time.elapsed_seconds(),
+            Err(_) => platform.time + time.delta_seconds(),
+        };
+        let curve = path.curve(platform.mode == PathMode::Loop);
+        let position = origin + platform.offset_at(&curve, platform.time);
+        platform.delta = position - transform.translation.truncate();
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Paths saved from the spline editor, by LDtk entity iid.
+#[derive(Asset, TypePath, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SplinePaths(pub HashMap<String, Spline>);
+impl SplinePaths {
+    /// Next to run_level.ldtk, so it's picked up by the asset server like the level itself.
+    pub const PATH: &'static str = "assets/platform.paths.ron";
+}
+
+/// Swaps in saved paths for new platforms and camera rails, and for all of them when the file changes.
+pub fn apply_saved_paths(
+    levels: Res<Levels>,
+    saved: Res<Assets<SplinePaths>>,
+    mut events: EventReader<AssetEvent<SplinePaths>>,
+    mut paths: Query<(&EntityIid, &mut Spline)>,
+) {
+    let changed = events.read().any(|event| {
+        event.is_modified(&levels.paths) || event.is_loaded_with_dependencies(&levels.paths)
+    });
+    let Some(saved) = saved.get(&levels.paths) else {
+        return;
+    };
+    for (iid, mut path) in paths.iter_mut() {
+        if !changed && !path.is_added() {
+            continue;
+        }
+        if let Some(saved) = saved.0.get(iid.as_str()) {
+            path.set_if_neq(saved.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_pong_returns_along_the_path() {
+        let path = Spline::new(
+            [Vec2::ZERO, vec2(32.0, 16.0), vec2(64.0, 0.0)],
+            SplineKind::CatmullRom,
+        );
+        let curve = path.curve(false);
+        let platform = MovingPlatform::new(PathMode::PingPong, Easing::Linear, 2.0);
+        assert!(platform.offset_at(&curve, 0.0).distance(Vec2::ZERO) < 1e-3);
+        assert!(platform.offset_at(&curve, 1.0).distance(vec2(32.0, 16.0)) < 1e-3);
+        assert!(platform.offset_at(&curve, 2.0).distance(vec2(64.0, 0.0)) < 1e-3);
+        assert!(platform.offset_at(&curve, 3.0).distance(vec2(32.0, 16.0)) < 1e-3);
+        assert!(platform.offset_at(&curve, 4.0).distance(Vec2::ZERO) < 1e-3);
+    }
+
+    #[test]
+    fn loop_comes_back_round_to_the_start() {
+        let path = Spline::new(
+            [Vec2::ZERO, vec2(32.0, 0.0), vec2(32.0, 32.0)],
+            SplineKind::CatmullRom,
+        );
+        let curve = path.curve(true);
+        let platform = MovingPlatform::new(PathMode::Loop, Easing::Linear, 3.0);
+        assert!(platform.offset_at(&curve, 1.0).distance(vec2(32.0, 0.0)) < 1e-3);
+        assert!(platform.offset_at(&curve, 2.9999).distance(Vec2::ZERO) < 1e-2);
+        assert!(platform.offset_at(&curve, 3.0).distance(Vec2::ZERO) < 1e-3);
+    }
+
+    #[test]
+    fn shipped_paths_parse() {
+        let paths: SplinePaths =
+            ron::de::from_str(include_str!("../../assets/platform.paths.ron")).unwrap();
+        for path in paths.0.values() {
+            assert!(!path.points.is_empty());
+        }
+    }
+}
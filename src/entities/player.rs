@@ -1,9 +1,11 @@
-use crate::animation::{RustAnimation, RustAnimationAtlas};
+use crate::animation::{AnimationSet, AnimationSource, RustAnimationAtlas};
+use crate::assets::Animations;
 use crate::camera::Follow;
-use crate::player_controls::PlayerState;
-use crate::player_movement::{Jump, Run, SideChecks};
+use crate::movement_profile::ControllerTuning;
+use crate::player_controls::{play_footsteps, PlayerState};
+use crate::player_movement::{Crouch, Jump, Run, SideChecks, WallJump, PLAYER_GROUP};
+use crate::pushing::PushGrab;
 use crate::PlayerText;
-use bevy::math::vec2;
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy_ecs_ldtk::prelude::*;
@@ -11,6 +13,23 @@ use bevy_rapier2d::prelude::*;
 
 #[derive(Component, Default)]
 pub struct PlayerMarker;
+/// Half extents of the standing collider.
+pub const PLAYER_HALF_SIZE: Vec2 = Vec2::new(7.75, 7.75);
+/// Anchor keeping the sprite's feet on the bottom of the collider.
+pub const PLAYER_ANCHOR: Vec2 = Vec2::new(0.0, -0.25);
+pub const PLAYER_SPRITE_SIZE: f32 = 32.0;
+/// No crouch frames in the sheet, squash the sprite down to `height` (a fraction of standing) instead,
+/// moving the anchor so the feet stay where they were.
+pub fn squash_player_sprite(sprite: &mut Sprite, height: f32) {
+    sprite.custom_size = Some(Vec2::new(PLAYER_SPRITE_SIZE, PLAYER_SPRITE_SIZE * height));
+    sprite.anchor = Anchor::Custom(Vec2::new(
+        PLAYER_ANCHOR.x,
+        -0.5 + (0.5 + PLAYER_ANCHOR.y) / height,
+    ));
+}
+/// Where the player goes back to on respawn, the last checkpoint touched.
+#[derive(Component, Default)]
+pub struct RespawnPoint(pub Vec2);
 #[derive(Bundle, LdtkEntity)]
 pub struct PlayerBundle {
     #[sprite_sheet_bundle("character.png", 32, 32, 3, 2, 0, 0, 2)]
@@ -20,10 +39,14 @@ pub struct PlayerBundle {
     side_checks: SideChecks,
     jump: Jump,
     run: Run,
+    wall_jump: WallJump,
+    crouch: Crouch,
+    push_grab: PushGrab,
     state: PlayerState,
     rigid_body: RigidBody,
     rust_animation_atlas: RustAnimationAtlas,
     collider: Collider,
+    collision_groups: CollisionGroups,
     locked_axis: LockedAxes,
     kinematic_character_controller: KinematicCharacterController,
     #[worldly]
@@ -33,31 +56,23 @@ impl Default for PlayerBundle {
     fn default() -> Self {
         PlayerBundle {
             rigid_body: RigidBody::KinematicVelocityBased,
-            rust_animation_atlas: RustAnimationAtlas::new([
-                RustAnimation::list([0], 0.1),
-                RustAnimation::list([0, 1, 2, 3], 0.1),
-                RustAnimation::list([0, 1, 2, 3, 4], 0.1),
-                RustAnimation::list([0], 0.1),
-                RustAnimation::list([5], 0.1),
-                RustAnimation::list([0, 1, 2, 3], 0.1),
-            ]),
-            collider: Collider::cuboid(7.75, 7.75),
+            rust_animation_atlas: Default::default(),
+            collider: Collider::cuboid(PLAYER_HALF_SIZE.x, PLAYER_HALF_SIZE.y),
+            collision_groups: CollisionGroups::new(PLAYER_GROUP, Group::ALL),
             locked_axis: LockedAxes::ROTATION_LOCKED,
             player: Default::default(),
             follow: Default::default(),
             side_checks: Default::default(),
             jump: Default::default(),
             run: Default::default(),
+            wall_jump: Default::default(),
+            crouch: Default::default(),
+            push_grab: Default::default(),
             state: Default::default(),
             sprite_bundle: Default::default(),
             kinematic_character_controller: KinematicCharacterController {
-                translation: None,
-                offset: CharacterLength::Relative(0.01),
-                normal_nudge_factor: 0.001,
-                slide: true,
-                snap_to_ground: Some(CharacterLength::Relative(0.03)),
-                apply_impulse_to_dynamic_bodies: true,
-                ..default()
+                filter_groups: Some(CollisionGroups::new(PLAYER_GROUP, Group::ALL)),
+                ..ControllerTuning::default().controller()
             },
             worldly: Default::default(),
         }
@@ -90,11 +105,19 @@ impl Default for PlayerChildBundle {
 }
 
 pub fn spawn_player(
-    mut new_player: Query<(Entity, &mut Sprite), Added<PlayerMarker>>,
+    mut new_player: Query<(Entity, &mut Sprite, &Transform), Added<PlayerMarker>>,
+    animations: Res<Animations>,
+    sets: Res<Assets<AnimationSet>>,
     mut commands: Commands,
 ) {
-    for (player, mut sprite) in new_player.iter_mut() {
-        sprite.anchor = Anchor::Custom(vec2(0.0, -0.25));
+    for (player, mut sprite, transform) in new_player.iter_mut() {
+        sprite.anchor = Anchor::Custom(PLAYER_ANCHOR);
+        let source = AnimationSource::new(animations.player.clone());
+        commands
+            .entity(player)
+            .insert(RespawnPoint(transform.translation.truncate()))
+            .insert((source.atlas(&sets), source))
+            .observe(play_footsteps);
         commands
             .spawn(PlayerChildBundle::default())
             .set_parent(player);
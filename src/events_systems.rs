@@ -1,10 +1,14 @@
+use crate::animation::Easing;
 use crate::assets::Sounds;
-use crate::entities::player::PlayerMarker;
+use crate::entities::flags::CHECKPOINT_COLOR;
+use crate::entities::player::{PlayerMarker, RespawnPoint};
+use crate::player_movement::{Jump, Run};
+use crate::tween::Tween;
 use crate::PlayerText;
-use crate::RaceTime;
 use crate::{BackgroundMusic, SoundEffects};
+use crate::{RaceTime, Split};
 use bevy::prelude::*;
-use bevy_ecs_ldtk::LevelSelection;
+use bevy_ecs_ldtk::{EntityIid, LevelSelection};
 use bevy_kira_audio::prelude::*;
 
 pub struct EventsPlugin;
@@ -12,6 +16,7 @@ impl Plugin for EventsPlugin {
     fn build(&self, app: &mut App) {
         app.observe(play_sounds);
         app.observe(player_touched_flags);
+        app.observe(respawn_player);
         app.observe(start_background_music);
     }
 }
@@ -26,6 +31,7 @@ pub struct SpawnMessageEvent {
 pub enum TouchedFlag {
     Start,
     Finish,
+    Checkpoint(Entity),
 }
 
 pub fn player_touched_flags(
@@ -33,10 +39,11 @@ pub fn player_touched_flags(
     mut text_query: Query<&mut Text, With<PlayerText>>,
     mut commands: Commands,
     mut race_time_query: Query<&mut RaceTime>,
-    player: Query<Entity, With<PlayerMarker>>,
+    player: Query<(Entity, &Transform), With<PlayerMarker>>,
+    flags: Query<(&GlobalTransform, &EntityIid)>,
     level_selection: Res<LevelSelection>,
 ) {
-    let player_entity = player.single();
+    let (player_entity, player_transform) = player.single();
     let mut msg = |msg: &str| {
         if let Ok(mut text) = text_query.get_single_mut() {
             text.sections[0].value = msg.into();
@@ -53,10 +60,10 @@ pub fn player_touched_flags(
             } else {
                 commands.trigger(PlaySoundEffect::Start);
                 msg("Run to the finish line!");
-                commands.entity(player_entity).insert(RaceTime {
-                    time: Time::default(),
-                    level: level_selection.clone(),
-                });
+                commands.entity(player_entity).insert((
+                    RaceTime::new(level_selection.clone()),
+                    RespawnPoint(player_transform.translation.truncate()),
+                ));
             }
         }
         TouchedFlag::Finish => {
@@ -67,15 +74,69 @@ pub fn player_touched_flags(
                         "You've finished! {:.3}",
                         time.time.elapsed_seconds()
                     ));
+                    commands.trigger(RaceFinished {
+                        level: time.level.clone(),
+                        time: time.time.elapsed_seconds(),
+                        splits: time.splits.clone(),
+                    });
                     commands.entity(player_entity).remove::<RaceTime>();
                 } else {
                     msg("Wrong flag silly goose.");
                 }
             }
         }
+        TouchedFlag::Checkpoint(checkpoint) => {
+            let Ok((transform, iid)) = flags.get(*checkpoint) else {
+                return;
+            };
+            commands
+                .entity(player_entity)
+                .insert(RespawnPoint(transform.translation().truncate()));
+            if let Ok(mut race_time) = race_time_query.get_single_mut() {
+                let already_touched = race_time
+                    .splits
+                    .iter()
+                    .any(|split| split.checkpoint == iid.as_str());
+                if race_time.level == *level_selection && !already_touched {
+                    let time = race_time.time.elapsed_seconds();
+                    race_time.splits.push(Split {
+                        checkpoint: iid.as_str().to_string(),
+                        time,
+                    });
+                    msg(&format!("Checkpoint! {:.3}", time));
+                    // Flash white and fade back to its tint.
+                    commands.entity(*checkpoint).insert(
+                        Tween::new(Color::WHITE, CHECKPOINT_COLOR, 0.5)
+                            .with_easing(Easing::QuadOut),
+                    );
+                }
+            }
+        }
     }
 }
 
+#[derive(Event)]
+pub struct RespawnPlayer;
+
+/// Puts the player back at the last checkpoint touched (or where they spawned), standing still.
+pub fn respawn_player(
+    _: Trigger<RespawnPlayer>,
+    mut player: Query<(&mut Transform, &RespawnPoint, &mut Jump, &mut Run), With<PlayerMarker>>,
+) {
+    if let Ok((mut transform, respawn_point, mut jump, mut run)) = player.get_single_mut() {
+        transform.translation = respawn_point.0.extend(transform.translation.z);
+        jump.stop();
+        run.stop();
+    }
+}
+
+#[derive(Event)]
+pub struct RaceFinished {
+    pub level: LevelSelection,
+    pub time: f32,
+    pub splits: Vec<Split>,
+}
+
 #[derive(Event)]
 pub struct StartBackgroundMusic;
 
@@ -84,10 +145,10 @@ pub fn start_background_music(
     sounds: Res<Sounds>,
     audio: Res<AudioChannel<BackgroundMusic>>,
 ) {
-    audio
-        .play(sounds.bgm.clone_weak())
-        .looped()
-        .with_volume(0.25);
+    if audio.is_playing_sound() {
+        return;
+    }
+    audio.play(sounds.bgm.clone_weak()).looped();
 }
 
 #[derive(Event)]
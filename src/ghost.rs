@@ -0,0 +1,188 @@
+use crate::animation::Interpolation;
+use crate::entities::player::{squash_player_sprite, PlayerMarker, PLAYER_ANCHOR};
+use crate::level_loader::level_iid_from_selection;
+use crate::player_controls::{AnimationDirection, PlayerState};
+use crate::player_movement::Crouch;
+use crate::records::Records;
+use crate::{GameState, RaceTime, RestoredRace};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use serde::{Deserialize, Serialize};
+
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            record_ghost.run_if(in_state(GameState::InGame)),
+        );
+        app.add_systems(
+            Update,
+            (start_ghost, play_ghost, stop_ghost).run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct GhostFrame {
+    pub position: [f32; 2],
+    pub state: PlayerState,
+    pub frame: usize,
+    #[serde(default = "standing_height")]
+    pub height: f32, // sprite squash, a fraction of standing like Crouch::height
+}
+fn standing_height() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Ghost {
+    pub tick: f32, // seconds between frames
+    pub frames: Vec<GhostFrame>,
+}
+impl Ghost {
+    pub fn new(tick: f32) -> Self {
+        Self {
+            tick,
+            frames: vec![],
+        }
+    }
+    /// Frame to show at `time` seconds into the race, position blended with the next frame.
+    pub fn sample(&self, time: f32) -> Option<GhostFrame> {
+        if self.frames.is_empty() || self.tick <= 0.0 {
+            return None;
+        }
+        let position = time / self.tick;
+        let index = (position.floor() as usize).min(self.frames.len() - 1);
+        let next = (index + 1).min(self.frames.len() - 1);
+        let mut frame = self.frames[index];
+        frame.position = Vec2::from(self.frames[index].position)
+            .linear(&Vec2::from(self.frames[next].position), position.fract())
+            .to_array();
+        Some(frame)
+    }
+}
+
+/// Collects frames for the player while a race is running.
+#[derive(Component, Clone)]
+pub struct GhostRecorder(pub Ghost);
+
+#[derive(Component)]
+pub struct GhostPlayback(pub Ghost);
+
+#[allow(clippy::type_complexity)]
+pub fn start_ghost(
+    player: Query<
+        (
+            Entity,
+            &RaceTime,
+            &Handle<Image>,
+            &TextureAtlas,
+            Option<Ref<RestoredRace>>,
+        ),
+        Added<RaceTime>,
+    >,
+    records: Res<Records>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut commands: Commands,
+) {
+    for (player, race_time, image, atlas, restored) in player.iter() {
+        if restored.is_some_and(|restored| restored.is_added()) {
+            continue;
+        }
+        commands.entity(player).insert(GhostRecorder(Ghost::new(
+            fixed_time.timestep().as_secs_f32(),
+        )));
+
+        let Some(ghost) =
+            level_iid_from_selection(&race_time.level, &ldtk_projects, &ldtk_project_assets)
+                .and_then(|level_iid| records.get(&level_iid))
+                .and_then(|record| record.ghost.clone())
+        else {
+            continue;
+        };
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgba(1.0, 1.0, 1.0, 0.4),
+                    anchor: Anchor::Custom(PLAYER_ANCHOR),
+                    ..default()
+                },
+                texture: image.clone(),
+                ..default()
+            },
+            TextureAtlas {
+                layout: atlas.layout.clone(),
+                index: 0,
+            },
+            GhostPlayback(ghost),
+            StateScoped(GameState::InGame),
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn record_ghost(
+    mut player: Query<
+        (
+            &Transform,
+            &PlayerState,
+            &TextureAtlas,
+            &Crouch,
+            &mut GhostRecorder,
+        ),
+        (With<PlayerMarker>, With<RaceTime>),
+    >,
+) {
+    for (transform, state, atlas, crouch, mut recorder) in player.iter_mut() {
+        recorder.0.frames.push(GhostFrame {
+            position: transform.translation.truncate().to_array(),
+            state: *state,
+            frame: atlas.index,
+            height: crouch.current_height(),
+        });
+    }
+}
+
+/// Shows the recorded sheet frames as they were, the ghost has no animator of its own to drift from them.
+pub fn play_ghost(
+    race_time: Query<&RaceTime>,
+    mut ghosts: Query<(
+        &GhostPlayback,
+        &mut Transform,
+        &mut Sprite,
+        &mut TextureAtlas,
+    )>,
+) {
+    let Ok(race_time) = race_time.get_single() else {
+        return;
+    };
+    for (ghost, mut transform, mut sprite, mut atlas) in ghosts.iter_mut() {
+        if let Some(frame) = ghost.0.sample(race_time.time.elapsed_seconds()) {
+            transform.translation = Vec2::from(frame.position).extend(-0.1);
+            atlas.index = frame.frame;
+            sprite.flip_x = frame.state.direction == AnimationDirection::Left;
+            squash_player_sprite(&mut sprite, frame.height);
+        }
+    }
+}
+
+pub fn stop_ghost(
+    race_time: Query<(), With<RaceTime>>,
+    recorders: Query<Entity, With<GhostRecorder>>,
+    ghosts: Query<Entity, With<GhostPlayback>>,
+    mut commands: Commands,
+) {
+    if !race_time.is_empty() {
+        return;
+    }
+    for entity in recorders.iter() {
+        commands.entity(entity).remove::<GhostRecorder>();
+    }
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
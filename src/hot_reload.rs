@@ -0,0 +1,99 @@
+use crate::entities::player::{spawn_player, PlayerMarker, RespawnPoint};
+use crate::ghost::GhostRecorder;
+use crate::player_movement::{Jump, Run};
+use crate::{GameState, RaceTime, RestoredRace};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+
+// With the `dev` feature the asset server watches run_level.ldtk, and bevy_ecs_ldtk respawns the whole
+// world when it changes. That takes the player with it, so carry them over to the new one.
+
+pub struct HotReloadPlugin;
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReloadedPlayer>();
+        app.add_systems(
+            Update,
+            (
+                save_player_on_reload,
+                restore_player_after_reload.after(spawn_player),
+            )
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// The player as it was right before the levels were respawned.
+#[derive(Resource, Default)]
+pub struct ReloadedPlayer(Option<PlayerSnapshot>);
+
+pub struct PlayerSnapshot {
+    translation: Vec3,
+    respawn_point: Vec2,
+    jump: Jump,
+    run: Run,
+    race_time: Option<RaceTime>,
+    ghost_recorder: Option<GhostRecorder>, // on the player, so it goes with the old one
+}
+
+#[allow(clippy::type_complexity)]
+pub fn save_player_on_reload(
+    mut ldtk_events: EventReader<AssetEvent<LdtkProject>>,
+    player: Query<
+        (
+            &Transform,
+            &RespawnPoint,
+            &Jump,
+            &Run,
+            Option<&RaceTime>,
+            Option<&GhostRecorder>,
+        ),
+        With<PlayerMarker>,
+    >,
+    mut reloaded: ResMut<ReloadedPlayer>,
+) {
+    if !ldtk_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }))
+    {
+        return;
+    }
+    let Ok((transform, respawn_point, jump, run, race_time, ghost_recorder)) = player.get_single()
+    else {
+        return;
+    };
+    info!("Level file changed, keeping the player where they are.");
+    reloaded.0 = Some(PlayerSnapshot {
+        translation: transform.translation,
+        respawn_point: respawn_point.0,
+        jump: jump.clone(),
+        run: run.clone(),
+        race_time: race_time.cloned(),
+        ghost_recorder: ghost_recorder.cloned(),
+    });
+}
+
+pub fn restore_player_after_reload(
+    mut new_player: Query<(Entity, &mut Transform, &mut Jump, &mut Run), Added<PlayerMarker>>,
+    mut reloaded: ResMut<ReloadedPlayer>,
+    mut commands: Commands,
+) {
+    let Ok((player, mut transform, mut jump, mut run)) = new_player.get_single_mut() else {
+        return;
+    };
+    let Some(snapshot) = reloaded.0.take() else {
+        return;
+    };
+    transform.translation = snapshot.translation;
+    *jump = snapshot.jump;
+    *run = snapshot.run;
+    let mut player = commands.entity(player);
+    player.insert(RespawnPoint(snapshot.respawn_point));
+    // The race carries on, with the ghost and input recordings it already had.
+    if let Some(race_time) = snapshot.race_time {
+        player.insert((race_time, RestoredRace));
+    }
+    if let Some(ghost_recorder) = snapshot.ghost_recorder {
+        player.insert(ghost_recorder);
+    }
+}
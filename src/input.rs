@@ -0,0 +1,289 @@
+use crate::menu::PauseState;
+use crate::storage;
+use crate::GameState;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+pub struct PlayerInputPlugin;
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMap::load());
+        app.init_resource::<ActionState>();
+        app.init_resource::<PlayerInput>();
+        app.add_systems(PreUpdate, update_action_state.after(InputSystem));
+        app.add_systems(
+            PreUpdate,
+            collect_player_input
+                .after(update_action_state)
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.add_systems(FixedPostUpdate, clear_pressed_input);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Jump,
+    Left,
+    Right,
+    Crouch,
+    Grab,
+    Restart,
+    Respawn,
+    Replay,
+    Pause,
+    ToggleDebug,
+    ToggleFps,
+    ToggleInspector,
+    ToggleTuning,
+    ToggleSplineEditor,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    Axis(GamepadAxisType, AxisDirection), // counts as pressed past InputMap::dead_zone
+}
+
+/// Which keys, buttons and sticks trigger each action. Loaded from input.ron so players can rebind.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct InputMap {
+    pub dead_zone: f32,
+    pub bindings: HashMap<Action, Vec<Binding>>,
+}
+impl Default for InputMap {
+    fn default() -> Self {
+        use Action::*;
+        use AxisDirection::*;
+        use Binding::*;
+        Self {
+            dead_zone: 0.5,
+            bindings: HashMap::from([
+                (
+                    Jump,
+                    vec![
+                        Key(KeyCode::KeyW),
+                        Key(KeyCode::ArrowUp),
+                        Key(KeyCode::Space),
+                        Button(GamepadButtonType::South),
+                    ],
+                ),
+                (
+                    Left,
+                    vec![
+                        Key(KeyCode::KeyA),
+                        Key(KeyCode::ArrowLeft),
+                        Button(GamepadButtonType::DPadLeft),
+                        Axis(GamepadAxisType::LeftStickX, Negative),
+                    ],
+                ),
+                (
+                    Right,
+                    vec![
+                        Key(KeyCode::KeyD),
+                        Key(KeyCode::ArrowRight),
+                        Button(GamepadButtonType::DPadRight),
+                        Axis(GamepadAxisType::LeftStickX, Positive),
+                    ],
+                ),
+                (
+                    Crouch,
+                    vec![
+                        Key(KeyCode::KeyS),
+                        Key(KeyCode::ArrowDown),
+                        Button(GamepadButtonType::DPadDown),
+                        Axis(GamepadAxisType::LeftStickY, Negative),
+                    ],
+                ),
+                (
+                    Grab,
+                    vec![Key(KeyCode::ShiftLeft), Button(GamepadButtonType::West)],
+                ),
+                (
+                    Restart,
+                    vec![Key(KeyCode::F1), Button(GamepadButtonType::Select)],
+                ),
+                (
+                    Respawn,
+                    vec![Key(KeyCode::KeyR), Button(GamepadButtonType::North)],
+                ),
+                (Replay, vec![Key(KeyCode::F5)]),
+                (
+                    Pause,
+                    vec![Key(KeyCode::Escape), Button(GamepadButtonType::Start)],
+                ),
+                (ToggleDebug, vec![Key(KeyCode::Digit2)]),
+                (ToggleFps, vec![Key(KeyCode::Digit1)]),
+                (ToggleInspector, vec![Key(KeyCode::Digit3)]),
+                (ToggleTuning, vec![Key(KeyCode::Digit4)]),
+                (ToggleSplineEditor, vec![Key(KeyCode::Digit5)]),
+            ]),
+        }
+    }
+}
+impl InputMap {
+    pub const PATH: &'static str = "input.ron";
+
+    /// Loads the bindings file, writing out the defaults the first time so there is something to edit.
+    /// Actions missing from an older file get their default bindings.
+    pub fn load() -> Self {
+        let defaults = Self::default();
+        match storage::load_ron::<Self>(Self::PATH) {
+            Some(mut input_map) => {
+                for (action, bindings) in defaults.bindings {
+                    input_map.bindings.entry(action).or_insert(bindings);
+                }
+                input_map
+            }
+            None => {
+                if storage::read(Self::PATH).is_none() {
+                    storage::save_ron(Self::PATH, &defaults);
+                }
+                defaults
+            }
+        }
+    }
+}
+
+/// Actions held this frame, resolved from every binding on every connected gamepad.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    previous: HashSet<Action>,
+}
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action) && !self.previous.contains(&action)
+    }
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.pressed.contains(&action) && self.previous.contains(&action)
+    }
+}
+
+pub fn update_action_state(
+    mut action_state: ResMut<ActionState>,
+    input_map: Res<InputMap>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+) {
+    let binding_pressed = |binding: &Binding| match *binding {
+        Binding::Key(key) => keys.pressed(key),
+        Binding::Button(button_type) => gamepads
+            .iter()
+            .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type))),
+        Binding::Axis(axis_type, direction) => gamepads.iter().any(|gamepad| {
+            let value = axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default();
+            match direction {
+                AxisDirection::Positive => value > input_map.dead_zone,
+                AxisDirection::Negative => value < -input_map.dead_zone,
+            }
+        }),
+    };
+    let pressed = input_map
+        .bindings
+        .iter()
+        .filter(|(_, bindings)| bindings.iter().any(binding_pressed))
+        .map(|(action, _)| *action)
+        .collect();
+    action_state.previous = std::mem::replace(&mut action_state.pressed, pressed);
+}
+
+/// Run condition like `input_toggle_active`, but for a rebindable action. Presses behind the pause menu
+/// don't count.
+pub fn action_toggle_active(
+    default: bool,
+    action: Action,
+) -> impl FnMut(Res<ActionState>, Option<Res<State<PauseState>>>) -> bool {
+    let mut active = default;
+    move |action_state: Res<ActionState>, pause: Option<Res<State<PauseState>>>| {
+        let paused = pause.is_some_and(|pause| *pause.get() == PauseState::Paused);
+        active ^= action_state.just_pressed(action) && !paused;
+        active
+    }
+}
+
+/// Input for one fixed tick. Movement reads this instead of the keyboard so runs can be replayed.
+#[derive(Resource, Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub jump: bool,
+    pub jump_pressed: bool, // latched until a fixed tick consumes it
+    pub crouch: bool,
+    pub left: bool,
+    pub right: bool,
+    pub respawn: bool, // latched like jump_pressed
+    pub grab: bool,
+}
+impl PlayerInput {
+    const JUMP: u8 = 1;
+    const JUMP_PRESSED: u8 = 2;
+    const CROUCH: u8 = 4;
+    const LEFT: u8 = 8;
+    const RIGHT: u8 = 16;
+    const RESPAWN: u8 = 32;
+    const GRAB: u8 = 64;
+
+    pub fn to_bits(self) -> u8 {
+        [
+            (self.jump, Self::JUMP),
+            (self.jump_pressed, Self::JUMP_PRESSED),
+            (self.crouch, Self::CROUCH),
+            (self.left, Self::LEFT),
+            (self.right, Self::RIGHT),
+            (self.respawn, Self::RESPAWN),
+            (self.grab, Self::GRAB),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |bits, (_, bit)| bits | bit)
+    }
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            jump: bits & Self::JUMP != 0,
+            jump_pressed: bits & Self::JUMP_PRESSED != 0,
+            crouch: bits & Self::CROUCH != 0,
+            left: bits & Self::LEFT != 0,
+            right: bits & Self::RIGHT != 0,
+            respawn: bits & Self::RESPAWN != 0,
+            grab: bits & Self::GRAB != 0,
+        }
+    }
+    /// -1 left, 1 right, None for neither or both.
+    pub fn direction(&self) -> Option<f32> {
+        match (self.left, self.right) {
+            (true, false) => Some(-1.0),
+            (false, true) => Some(1.0),
+            _ => None,
+        }
+    }
+}
+
+pub fn collect_player_input(mut player_input: ResMut<PlayerInput>, action_state: Res<ActionState>) {
+    player_input.jump = action_state.pressed(Action::Jump);
+    player_input.jump_pressed |= action_state.just_pressed(Action::Jump);
+    player_input.crouch = action_state.pressed(Action::Crouch);
+    player_input.left = action_state.pressed(Action::Left);
+    player_input.right = action_state.pressed(Action::Right);
+    player_input.respawn |= action_state.just_pressed(Action::Respawn);
+    player_input.grab = action_state.pressed(Action::Grab);
+}
+
+pub fn clear_pressed_input(mut player_input: ResMut<PlayerInput>) {
+    player_input.jump_pressed = false;
+    player_input.respawn = false;
+}
@@ -3,7 +3,12 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 use bevy_rapier2d::prelude::*;
-use entities::{crates::*, flags::*, message::*, player::*};
+use entities::{
+    camera_rail::*, crates::*, flags::*, hazard::*, message::*, platform::*, player::*,
+};
+use ron_asset::RonAssetLoader;
+use std::collections::HashSet;
+use tile_colliders::{merge_cells, merge_slopes, Cell, Slope};
 
 pub struct RFFLevelPlugin;
 impl Plugin for RFFLevelPlugin {
@@ -16,10 +21,15 @@ impl Plugin for RFFLevelPlugin {
             ..default()
         });
         app.insert_resource(LevelSelection::Uid(0));
+        app.init_resource::<LevelStart>();
         app.register_ldtk_entity::<PlayerBundle>("Player");
         app.register_ldtk_entity::<Crate>("Crate");
         app.register_ldtk_entity::<StartFlag>("Start");
         app.register_ldtk_entity::<FinishFlag>("Finish");
+        app.register_ldtk_entity::<CheckpointFlag>("Checkpoint");
+        app.register_ldtk_entity::<HazardBundle>("Hazard");
+        app.register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform");
+        app.register_ldtk_entity::<CameraRailBundle>("CameraRail");
         app.register_ldtk_entity::<WorldMessageBundle>("WorldMessage");
         app.add_systems(
             Update,
@@ -29,8 +39,19 @@ impl Plugin for RFFLevelPlugin {
                 spawn_flags,
                 spawn_world_message,
                 level_selection_follow_player,
+                move_player_to_level_start,
+                apply_saved_paths,
             )
-                .run_if(in_state(GameState::LoadGame)),
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.init_asset::<SplinePaths>();
+        app.register_asset_loader(RonAssetLoader::<SplinePaths>::new(&["paths.ron"]));
+        app.add_systems(
+            FixedUpdate,
+            move_platforms
+                .after(advance_race_timer)
+                .before(player_wall_ceiling_checks)
+                .run_if(in_state(GameState::InGame)),
         );
     }
 }
@@ -41,7 +62,11 @@ fn level_selection_follow_player(
     ldtk_projects: Query<&Handle<LdtkProject>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
     mut level_selection: ResMut<LevelSelection>,
+    level_start: Res<LevelStart>,
 ) {
+    if level_start.0.is_some() {
+        return;
+    }
     if let Ok(player_transform) = players.get_single() {
         let ldtk_project = ldtk_project_assets
             .get(ldtk_projects.single())
@@ -70,71 +95,119 @@ fn level_selection_follow_player(
     }
 }
 
-pub struct TiledCollisionBuilder {
-    building: bool,
-    position: Vec2,
-    size: Vec2,
-    height: f32,
-    tile_size: Vec2,
-    offset: Vec2,
+/// Level picked from the level select, the player is moved to its start flag once it has spawned.
+#[derive(Resource, Default)]
+pub struct LevelStart(pub Option<LevelIid>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn move_player_to_level_start(
+    mut level_start: ResMut<LevelStart>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut RespawnPoint,
+            &mut Jump,
+            &mut Run,
+        ),
+        With<PlayerMarker>,
+    >,
+    starts: Query<(Entity, &GlobalTransform), With<Start>>,
+    finishes: Query<(Entity, &GlobalTransform), With<Finish>>,
+    parents: Query<&Parent>,
+    levels: Query<&LevelIid>,
+    mut commands: Commands,
+) {
+    let Some(target) = level_start.0.clone() else {
+        return;
+    };
+    // The player lives in the first level, so wait for it before switching away.
+    let Ok((player, mut transform, mut respawn_point, mut jump, mut run)) = player.get_single_mut()
+    else {
+        return;
+    };
+    *level_selection = LevelSelection::Iid(target.clone());
+
+    let in_target = |entity: Entity| {
+        parents
+            .iter_ancestors(entity)
+            .any(|ancestor| levels.get(ancestor) == Ok(&target))
+    };
+    let Some((_, start)) = starts.iter().find(|(entity, _)| in_target(*entity)) else {
+        return;
+    };
+    // Stand a little behind the flag so the race starts when you run through it.
+    let behind = finishes
+        .iter()
+        .find(|(entity, _)| in_target(*entity))
+        .map(|(_, finish)| -(finish.translation().x - start.translation().x).signum() * 24.0)
+        .unwrap_or(0.0);
+
+    let position = start.translation().truncate() + vec2(behind, 0.0);
+    transform.translation = position.extend(transform.translation.z);
+    respawn_point.0 = position;
+    jump.stop();
+    run.stop();
+    commands.entity(player).remove::<RaceTime>();
+    level_start.0 = None;
 }
-impl Default for TiledCollisionBuilder {
-    fn default() -> Self {
-        Self {
-            size: Vec2::ZERO,
-            building: false,
-            height: 32.0,
-            position: Vec2::ZERO,
-            tile_size: Vec2::new(16.0, 16.0),
-            offset: Vec2::ZERO,
-        }
+
+/// How far below the current level the player can fall before dying.
+const FALL_OUT_MARGIN: f32 = 64.0;
+
+pub fn kill_out_of_bounds(
+    players: Query<&GlobalTransform, With<PlayerMarker>>,
+    levels: Query<(&LevelIid, &GlobalTransform)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    level_selection: Res<LevelSelection>,
+    mut commands: Commands,
+) {
+    let Ok(player_transform) = players.get_single() else {
+        return;
+    };
+    let Some(ldtk_project) = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+    else {
+        return;
+    };
+    let Some(level) = ldtk_project.find_raw_level_by_level_selection(&level_selection) else {
+        return;
+    };
+    let Some((_, level_transform)) = levels.iter().find(|(iid, _)| iid.get() == &level.iid) else {
+        return;
+    };
+    let position = player_transform.translation().truncate();
+    let min = level_transform.translation().truncate();
+    let max = min + vec2(level.px_wid as f32, level.px_hei as f32);
+    // Only the bottom and the sides past the bottom, walking into a neighbor level is fine.
+    if position.y < min.y - FALL_OUT_MARGIN
+        && position.x > min.x - FALL_OUT_MARGIN
+        && position.x < max.x + FALL_OUT_MARGIN
+    {
+        commands.trigger(PlayerDied { reset_race: false });
     }
 }
-impl TiledCollisionBuilder {
-    pub fn build(&mut self) -> (SpatialBundle, Collider, RigidBody) {
-        let position = self.position + self.offset;
-        let components = (
-            SpatialBundle {
-                transform: Transform::from_xyz(
-                    position.x + (self.size.x / 2.0) - self.tile_size.x / 2.0,
-                    position.y - (self.height - self.tile_size.y) / 2.0,
-                    0.0,
-                ),
-                ..default()
-            },
-            Collider::cuboid(self.size.x / 2.0, self.height / 2.0),
-            RigidBody::Fixed,
-        );
-        self.reset();
-        components
-    }
-    pub fn set_offset(&mut self, offset: Vec2) {
-        self.offset = offset;
-    }
-    pub fn begin(&mut self, pos: Vec2, tile_size: Vec2, height: f32) {
-        self.size = tile_size;
-        self.position = pos;
-        self.tile_size = tile_size;
-        self.height = height;
-        self.building = true;
-    }
-    pub fn reset(&mut self) {
-        let b = Self::default();
-        self.building = b.building;
-        self.tile_size = b.tile_size;
-        self.size = b.size;
-        self.height = b.height;
-        self.position = b.position;
-        self.offset = b.offset;
-    }
-    pub fn extend(&mut self) {
-        if !self.building {
-            return;
-        }
-        self.size.x += self.tile_size.x;
-    }
+
+pub fn level_iid_from_selection(
+    level_selection: &LevelSelection,
+    ldtk_projects: &Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: &Assets<LdtkProject>,
+) -> Option<String> {
+    ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+        .and_then(|project| project.find_raw_level_by_level_selection(level_selection))
+        .map(|level| level.iid.clone())
 }
 
+/// How thick the collider of a one way platform is, at the top of its tile.
+const ONE_WAY_THICKNESS: f32 = 4.0;
+
 pub fn dynamic_collision_layer_building(
     mut cmds: Commands,
     q_tile: Query<(Entity, &TileStorage, &TilemapGridSize, &LayerMetadata), Added<TileStorage>>,
@@ -147,52 +220,111 @@ pub fn dynamic_collision_layer_building(
         let tile_size = vec2(tilemap_gridsize.x, tilemap_gridsize.y);
         let level_size = (tile_storage.size.x, tile_storage.size.y);
 
-        let mut cells: HashMap<(i32, i32), f32> = HashMap::new();
+        let mut cells: HashMap<(i32, i32), Cell> = HashMap::new();
+        let mut one_way_cells: HashSet<(i32, i32)> = HashSet::new();
+        let mut hazard_cells: HashMap<(i32, i32), Cell> = HashMap::new();
+        let mut slopes: HashMap<(i32, i32), Slope> = HashMap::new();
         for y in 0..level_size.1 {
             for x in 0..level_size.0 {
                 let tile_position = TilePos::new(x, y);
                 if let Some(tile) = tile_storage.checked_get(&tile_position) {
                     if let Ok(tile_metadata) = data.get(tile) {
-                        match tile_metadata.data.as_str() {
-                            "collider" => {
-                                cells.insert((x as i32, y as i32), tile_size.y);
+                        let tag = tile_metadata.data.as_str();
+                        if let Some(cell) = Cell::from_tile_data(tag) {
+                            cells.insert((x as i32, y as i32), cell);
+                            continue;
+                        }
+                        match tag {
+                            "hazard" => {
+                                hazard_cells.insert((x as i32, y as i32), Cell::FULL);
                             }
-                            "half_collider" => {
-                                cells.insert((x as i32, y as i32), tile_size.y * 0.5);
+                            "one_way" => {
+                                one_way_cells.insert((x as i32, y as i32));
                             }
-                            "quarter_collider" => {
-                                cells.insert((x as i32, y as i32), tile_size.y * 0.25);
+                            tag => {
+                                match Slope::from_tile_data(tag) {
+                                    Some(slope) => {
+                                        slopes.insert((x as i32, y as i32), slope);
+                                    }
+                                    None if tag.starts_with("slope_") => {
+                                        warn!("Unknown slope tag {} on tile {}, {}, it has no collider.", tag, x, y);
+                                    }
+                                    None => {}
+                                }
                             }
-                            _ => {}
                         }
                     }
                 }
             }
         }
 
-        let mut builder = TiledCollisionBuilder::default();
-
+        for rect in merge_cells(&cells) {
+            let (min, max) = rect.bounds(tile_size.to_array());
+            let (min, max) = (Vec2::from(min), Vec2::from(max));
+            let half_size = (max - min) / 2.0;
+            cmds.spawn((
+                SpatialBundle::from_transform(Transform::from_translation(
+                    (min + half_size).extend(0.0),
+                )),
+                Collider::cuboid(half_size.x, half_size.y),
+                RigidBody::Fixed,
+            ))
+            .set_parent(level_entity);
+        }
+        // Slopes running on from one tile to the next share one hull, so there's no seam between them.
+        for points in merge_slopes(&slopes, &cells) {
+            let points = points
+                .into_iter()
+                .map(|p| (Vec2::from(p) - Vec2::splat(0.5)) * tile_size)
+                .collect::<Vec<_>>();
+            if let Some(collider) = Collider::convex_hull(&points) {
+                cmds.spawn((SpatialBundle::default(), collider, RigidBody::Fixed))
+                    .set_parent(level_entity);
+            }
+        }
+        // Hazard tiles merge the same way, into one sensor per rectangle.
+        for rect in merge_cells(&hazard_cells) {
+            let (min, max) = rect.bounds(tile_size.to_array());
+            let (min, max) = (Vec2::from(min), Vec2::from(max));
+            let half_size = (max - min) / 2.0;
+            cmds.spawn((
+                SpatialBundle::from_transform(Transform::from_translation(
+                    (min + half_size).extend(0.0),
+                )),
+                Collider::cuboid(half_size.x, half_size.y),
+                Hazard::default(),
+                HazardSensorBundle::default(),
+            ))
+            .set_parent(level_entity);
+        }
+        // One way platforms merge along rows too, as thin slabs on top of their tiles.
         for y in 0..level_size.1 as i32 {
-            for x in 0..level_size.0 as i32 {
-                let pos = vec2(x as f32 * tile_size.x, y as f32 * tile_size.y);
-                let cell = cells.get(&(x, y));
-                match (cell.is_some(), builder.building) {
-                    (true, false) => {
-                        builder.begin(pos, tile_size, *cell.unwrap()); //We checked if it's some first.
-                    }
-                    (true, true) => {
-                        builder.extend();
-                    }
-                    (false, false) => {}
-                    (false, true) => {
-                        cmds.spawn(builder.build()).set_parent(level_entity);
-                    }
+            let mut x = 0;
+            while x < level_size.0 as i32 {
+                if !one_way_cells.contains(&(x, y)) {
+                    x += 1;
+                    continue;
                 }
-            }
-            if builder.building {
-                cmds.spawn(builder.build()).set_parent(level_entity);
+                let start = x;
+                while one_way_cells.contains(&(x, y)) {
+                    x += 1;
+                }
+                let width = (x - start) as f32 * tile_size.x;
+                cmds.spawn((
+                    SpatialBundle::from_transform(Transform::from_xyz(
+                        start as f32 * tile_size.x - tile_size.x / 2.0 + width / 2.0,
+                        y as f32 * tile_size.y + (tile_size.y - ONE_WAY_THICKNESS) / 2.0,
+                        0.0,
+                    )),
+                    Collider::cuboid(width / 2.0, ONE_WAY_THICKNESS / 2.0),
+                    CollisionGroups::default(),
+                    RigidBody::Fixed,
+                    OneWayPlatform,
+                ))
+                .set_parent(level_entity);
             }
         }
+
         let data = level_data.level1.path();
         info!("Finished Building Colliders for level. {:?}", data);
     }
@@ -1,22 +1,42 @@
 pub mod animation;
 pub mod assets;
 pub mod camera;
+pub mod death;
 pub mod events_systems;
+pub mod ghost;
+#[cfg(feature = "dev")]
+pub mod hot_reload;
+pub mod input;
 pub mod level_loader;
+pub mod menu;
+pub mod movement_profile;
 pub mod player_controls;
 pub mod player_movement;
+pub mod pushing;
+pub mod records;
+pub mod replay;
+pub mod ron_asset;
+pub mod spline_editor;
+pub mod splits;
+pub mod storage;
+pub mod tile_colliders;
+pub mod tuning_panel;
+pub mod tween;
 pub mod entities {
+    pub mod camera_rail;
     pub mod crates;
     pub mod flags;
+    pub mod hazard;
     pub mod message;
+    pub mod platform;
     pub mod player;
 }
+use crate::entities::hazard::Hazard;
 use crate::entities::player::PlayerMarker;
 use animation::*;
 use assets::*;
 use bevy::asset::AssetMetaCheck;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use bevy::input::common_conditions::input_toggle_active;
 use bevy::math::vec2;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
@@ -25,12 +45,26 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_kira_audio::{AudioApp, AudioPlugin};
 use bevy_rapier2d::prelude::*;
 use camera::*;
+use death::*;
 use events_systems::*;
+use ghost::*;
+#[cfg(feature = "dev")]
+use hot_reload::*;
+use input::*;
 use iyes_perf_ui::prelude::*;
 use level_loader::*;
+use menu::*;
+use movement_profile::*;
 use player_controls::*;
 use player_movement::*;
+use records::*;
+use replay::*;
+use serde::{Deserialize, Serialize};
+use spline_editor::*;
+use splits::*;
 use std::collections::HashMap;
+use tuning_panel::*;
+use tween::*;
 use wasm_bindgen::prelude::*;
 
 #[derive(Component)]
@@ -39,16 +73,40 @@ pub struct PlayerText;
 pub struct Start;
 #[derive(Component, Default)]
 pub struct Finish;
-#[derive(Component)]
+#[derive(Component, Default)]
+pub struct Checkpoint;
+/// Put back on the player with its `RaceTime` after the level file is hot reloaded. The race carries on,
+/// so whatever gets set up when one starts leaves it alone.
+#[derive(Component, Default)]
+pub struct RestoredRace;
+#[derive(Component, Clone)]
 pub struct RaceTime {
     time: Time,
     level: LevelSelection,
+    splits: Vec<Split>,
+}
+impl RaceTime {
+    pub fn new(level: LevelSelection) -> Self {
+        Self {
+            time: Time::default(),
+            level,
+            splits: vec![],
+        }
+    }
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Split {
+    pub checkpoint: String, // entity iid of the checkpoint
+    pub time: f32,
 }
 #[derive(Resource)]
 pub struct BackgroundMusic;
 #[derive(Resource)]
 pub struct SoundEffects;
 
+/// Physics, movement and the race timer all tick at this rate so runs can be replayed exactly.
+pub const PHYSICS_HZ: f64 = 120.0;
+
 #[wasm_bindgen(start)]
 pub fn run() {
     App::new()
@@ -76,37 +134,62 @@ struct RunGame;
 impl Plugin for RunGame {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>();
+        app.enable_state_scoped_entities::<GameState>();
         app.add_plugins(LoadingPlugin);
-        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
-            Distance::PIXELS_PER_METER,
-        ));
+        app.add_plugins(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(Distance::PIXELS_PER_METER)
+                .in_fixed_schedule(),
+        );
+        app.insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ));
         app.add_plugins(RapierDebugRenderPlugin {
             enabled: false,
             ..default()
         });
         app.add_plugins(RFFLevelPlugin);
         app.add_plugins(FrameTimeDiagnosticsPlugin);
-        app.add_plugins(PerfUiPlugin::default());
+        app.add_plugins(PerfUiPlugin);
         app.add_plugins(RustAnimationPlugin);
+        app.add_plugins(TweenPlugin);
         app.add_plugins(
-            WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Digit3)),
+            WorldInspectorPlugin::default()
+                .run_if(action_toggle_active(false, Action::ToggleInspector)),
         );
-        app.add_plugins(AudioPlugin::default());
+        app.add_plugins(AudioPlugin);
         app.add_plugins(CameraPlugin);
         app.add_plugins(EventsPlugin);
+        app.add_plugins(PlayerInputPlugin);
         app.add_plugins(PlayerControlPlugin);
+        app.add_plugins(RecordsPlugin);
+        app.add_plugins(GhostPlugin);
+        app.add_plugins(ReplayPlugin);
+        app.add_plugins(SplitsPlugin);
+        app.add_plugins(DeathPlugin);
+        app.add_plugins(MovementProfilePlugin);
+        app.add_plugins(TuningPanelPlugin);
+        app.add_plugins(SplineEditorPlugin);
+        #[cfg(feature = "dev")]
+        app.add_plugins(HotReloadPlugin);
+        app.add_plugins(MenuPlugin);
         app.add_audio_channel::<BackgroundMusic>();
         app.add_audio_channel::<SoundEffects>();
         app.insert_resource(MousePosition(Vec2::ZERO));
+        app.add_systems(OnEnter(GameState::Menu), start_menu_music);
         app.add_systems(OnEnter(GameState::LoadGame), setup);
+        app.add_systems(OnExit(GameState::InGame), cleanup_game);
         app.add_systems(PreUpdate, update_mouse_position);
         app.add_systems(
-            Update,
-            (detect_flags, advance_race_timer).run_if(in_state(GameState::LoadGame)),
+            FixedUpdate,
+            (
+                advance_race_timer.before(PhysicsSet::SyncBackend),
+                detect_flags.after(PhysicsSet::Writeback),
+                kill_out_of_bounds.after(PhysicsSet::Writeback),
+            )
+                .run_if(in_state(GameState::InGame)),
         );
     }
 }
 
+// Loading assets -> Menu -> LoadGame spawns the world for one frame -> InGame
 #[derive(Default, States, Debug, Eq, PartialEq, Hash, Clone)]
 pub enum GameState {
     #[default]
@@ -144,6 +227,8 @@ pub fn detect_flags(
     mut collision_events: EventReader<CollisionEvent>,
     start: Query<Entity, With<Start>>,
     finish: Query<Entity, With<Finish>>,
+    checkpoints: Query<Entity, With<Checkpoint>>,
+    hazards: Query<(Entity, &Hazard)>,
     mut commands: Commands,
 ) {
     let player_entity = if let Ok(entity) = player.get_single() {
@@ -154,7 +239,7 @@ pub fn detect_flags(
     for collision in collision_events.read() {
         if let CollisionEvent::Started(e1, e2, _) = collision {
             if ![*e1, *e2].contains(&player_entity) {
-                return;
+                continue;
             }
             for s in start.iter() {
                 if [*e1, *e2].contains(&s) {
@@ -166,6 +251,18 @@ pub fn detect_flags(
                     commands.trigger(TouchedFlag::Finish);
                 }
             }
+            for c in checkpoints.iter() {
+                if [*e1, *e2].contains(&c) {
+                    commands.trigger(TouchedFlag::Checkpoint(c));
+                }
+            }
+            for (h, hazard) in hazards.iter() {
+                if [*e1, *e2].contains(&h) {
+                    commands.trigger(PlayerDied {
+                        reset_race: hazard.reset_race,
+                    });
+                }
+            }
         }
     }
 }
@@ -174,73 +271,41 @@ fn setup(
     mut cmds: Commands,
     mut rapier_config: ResMut<RapierConfiguration>,
     level_query: Res<Levels>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut ldtk_settings: ResMut<LdtkSettings>,
+    settings: Res<Settings>,
 ) {
     //Setup Physics
-    rapier_config.gravity.y = -300.0;
+    rapier_config.gravity.y = DEFAULT_GRAVITY;
     rapier_config.timestep_mode = TimestepMode::Fixed {
-        dt: 1. / 120.,
+        dt: (1. / PHYSICS_HZ) as f32,
         substeps: 4,
     };
 
-    cmds.spawn(LdtkWorldBundle {
-        ldtk_handle: level_query.level1.clone(),
-        ..default()
-    });
+    // Always spawn the first level, it holds the player. Level select moves them afterwards.
+    *level_selection = LevelSelection::Uid(0);
+    ldtk_settings.level_spawn_behavior = LevelSpawnBehavior::UseWorldTranslation {
+        load_level_neighbors: !settings.isolated_levels,
+    };
 
+    cmds.spawn((
+        LdtkWorldBundle {
+            ldtk_handle: level_query.level1.clone(),
+            ..default()
+        },
+        StateScoped(GameState::InGame),
+    ));
+
+    next_state.set(GameState::InGame);
+}
+
+fn start_menu_music(mut cmds: Commands) {
     cmds.trigger(StartBackgroundMusic);
+}
 
-    // //UI Attempts
-    // let root_row = cmds
-    //     .spawn(NodeBundle {
-    //         style: Style {
-    //             flex_direction: FlexDirection::Row,
-    //             left: Val::Px(4.),
-    //             top: Val::Px(4.),
-    //             ..default()
-    //         },
-    //         ..default()
-    //     })
-    //     .id();
-    // let column1 = cmds
-    //     .spawn(NodeBundle {
-    //         style: Style {
-    //             flex_direction: FlexDirection::Column,
-    //             left: Val::Px(4.),
-    //             top: Val::Px(4.),
-    //             ..default()
-    //         },
-    //         ..default()
-    //     })
-    //     .id();
-    // let column2 = cmds
-    //     .spawn(NodeBundle {
-    //         style: Style {
-    //             flex_direction: FlexDirection::Column,
-    //             left: Val::Px(4.),
-    //             top: Val::Px(4.),
-    //             ..default()
-    //         },
-    //         ..default()
-    //     })
-    //     .id();
-    // cmds.entity(root_row).add_child(column1);
-    // cmds.entity(root_row).add_child(column2);
-    // let button_labels1 = ["Jump+", "FDrag+"];
-    // let button_labels2 = ["Jump-", "FDrag-"];
-    // cmds.entity(column1).with_children(|parent| {
-    //     for label in button_labels1 {
-    //         parent.spawn(button()).with_children(|parent| {
-    //             parent.spawn(text(label));
-    //         });
-    //     }
-    // });
-    // cmds.entity(column2).with_children(|parent| {
-    //     for label in button_labels2 {
-    //         parent.spawn(button()).with_children(|parent| {
-    //             parent.spawn(text(label));
-    //         });
-    //     }
-    // });
+fn cleanup_game(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
 }
 
 pub fn button() -> ButtonBundle {
@@ -0,0 +1,382 @@
+use crate::assets::{Levels, Tuning};
+use crate::input::{Action, ActionState, PlayerInput};
+use crate::level_loader::LevelStart;
+use crate::movement_profile::MovementProfiles;
+use crate::records::Records;
+use crate::storage;
+use crate::{button, text, BackgroundMusic, GameState, SoundEffects};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_kira_audio::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<MenuState>();
+        app.add_sub_state::<PauseState>();
+        app.enable_state_scoped_entities::<MenuState>();
+        app.enable_state_scoped_entities::<PauseState>();
+        app.insert_resource(Settings::load());
+        app.add_systems(Startup, apply_audio_settings);
+        app.add_systems(OnEnter(MenuState::LevelSelect), spawn_level_select_menu);
+        app.add_systems(OnEnter(MenuState::Title), spawn_title_menu);
+        app.add_systems(OnEnter(MenuState::Options), spawn_options_menu);
+        app.add_systems(OnEnter(PauseState::Paused), (pause_game, spawn_pause_menu));
+        app.add_systems(OnExit(PauseState::Paused), resume_game);
+        app.add_systems(
+            Update,
+            (
+                menu_button_colors,
+                menu_button_actions,
+                update_options_text.run_if(resource_changed::<Settings>),
+            ),
+        );
+        app.add_systems(Update, toggle_pause.run_if(in_state(GameState::InGame)));
+    }
+}
+
+#[derive(SubStates, Default, Debug, Eq, PartialEq, Hash, Clone)]
+#[source(GameState = GameState::Menu)]
+pub enum MenuState {
+    #[default]
+    Title,
+    LevelSelect,
+    Options,
+}
+
+#[derive(SubStates, Default, Debug, Eq, PartialEq, Hash, Clone)]
+#[source(GameState = GameState::InGame)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub music_volume: f64,
+    pub sfx_volume: f64,
+    #[serde(default)]
+    pub isolated_levels: bool, // only load the current level, not its neighbors
+    #[serde(default = "default_movement_profile")]
+    pub movement_profile: String, // name in movement.profiles.ron, levels can override it
+}
+fn default_movement_profile() -> String {
+    MovementProfiles::DEFAULT.into()
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 0.25,
+            sfx_volume: 1.0,
+            isolated_levels: false,
+            movement_profile: default_movement_profile(),
+        }
+    }
+}
+impl Settings {
+    pub const PATH: &'static str = "settings.ron";
+    pub fn load() -> Self {
+        storage::load_ron(Self::PATH).unwrap_or_default()
+    }
+    pub fn save(&self) {
+        storage::save_ron(Self::PATH, self);
+    }
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum MenuButton {
+    Play,
+    LevelSelect,
+    Level(String), // level iid
+    Options,
+    IsolatedLevels,
+    MovementProfile,
+    Back,
+    MusicUp,
+    MusicDown,
+    SfxUp,
+    SfxDown,
+    Resume,
+    QuitToMenu,
+}
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Play => "Play",
+            MenuButton::LevelSelect => "Levels",
+            MenuButton::Level(_) => "Level",
+            MenuButton::Options => "Options",
+            MenuButton::IsolatedLevels => "Loading",
+            MenuButton::MovementProfile => "Feel",
+            MenuButton::Back => "Back",
+            MenuButton::MusicUp => "Music+",
+            MenuButton::MusicDown => "Music-",
+            MenuButton::SfxUp => "Sfx+",
+            MenuButton::SfxDown => "Sfx-",
+            MenuButton::Resume => "Resume",
+            MenuButton::QuitToMenu => "Menu",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct OptionsText;
+
+const BUTTON_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.2, 0.5, 0.2);
+
+pub fn menu_root() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+pub fn menu_row() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+pub fn spawn_button(parent: &mut ChildBuilder, menu_button: MenuButton) {
+    let label = menu_button.label();
+    parent
+        .spawn((button(), menu_button))
+        .with_children(|parent| {
+            parent.spawn(text(label));
+        });
+}
+
+pub fn spawn_title_menu(mut cmds: Commands) {
+    cmds.spawn((menu_root(), StateScoped(MenuState::Title)))
+        .with_children(|parent| {
+            parent.spawn(text("Run for Fun: With Physics!"));
+            spawn_button(parent, MenuButton::Play);
+            spawn_button(parent, MenuButton::LevelSelect);
+            spawn_button(parent, MenuButton::Options);
+        });
+}
+
+pub fn spawn_options_menu(mut cmds: Commands, settings: Res<Settings>) {
+    cmds.spawn((menu_root(), StateScoped(MenuState::Options)))
+        .with_children(|parent| {
+            parent.spawn((text(options_text(&settings)), OptionsText));
+            parent.spawn(menu_row()).with_children(|parent| {
+                spawn_button(parent, MenuButton::MusicDown);
+                spawn_button(parent, MenuButton::MusicUp);
+            });
+            parent.spawn(menu_row()).with_children(|parent| {
+                spawn_button(parent, MenuButton::SfxDown);
+                spawn_button(parent, MenuButton::SfxUp);
+            });
+            spawn_button(parent, MenuButton::IsolatedLevels);
+            spawn_button(parent, MenuButton::MovementProfile);
+            spawn_button(parent, MenuButton::Back);
+        });
+}
+
+pub fn spawn_level_select_menu(
+    mut cmds: Commands,
+    levels: Res<Levels>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    records: Res<Records>,
+) {
+    let Some(project) = ldtk_project_assets.get(&levels.level1) else {
+        return;
+    };
+    cmds.spawn((menu_root(), StateScoped(MenuState::LevelSelect)))
+        .with_children(|parent| {
+            parent.spawn(text("Levels"));
+            for level in project.iter_raw_levels() {
+                let best = records
+                    .best(&level.iid)
+                    .map(|best| format!("{:.3}", best))
+                    .unwrap_or_else(|| "--".to_string());
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(240.0),
+                                ..button().style
+                            },
+                            ..button()
+                        },
+                        MenuButton::Level(level.iid.clone()),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(text(format!("{}  {}", level.identifier, best)));
+                    });
+            }
+            spawn_button(parent, MenuButton::Back);
+        });
+}
+
+pub fn spawn_pause_menu(mut cmds: Commands) {
+    cmds.spawn((
+        menu_root(),
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        StateScoped(PauseState::Paused),
+    ))
+    .with_children(|parent| {
+        parent.spawn(text("Paused"));
+        spawn_button(parent, MenuButton::Resume);
+        spawn_button(parent, MenuButton::QuitToMenu);
+    });
+}
+
+fn options_text(settings: &Settings) -> String {
+    format!(
+        "Music {:.0}%  Sfx {:.0}%  Levels: {}  Feel: {}",
+        settings.music_volume * 100.0,
+        settings.sfx_volume * 100.0,
+        match settings.isolated_levels {
+            true => "isolated",
+            false => "with neighbors",
+        },
+        settings.movement_profile
+    )
+}
+
+pub fn update_options_text(
+    settings: Res<Settings>,
+    mut text_query: Query<&mut Text, With<OptionsText>>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = options_text(&settings);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn menu_button_colors(
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<MenuButton>),
+    >,
+) {
+    for (interaction, mut color) in buttons.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR,
+            Interaction::Hovered => BUTTON_HOVER_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn menu_button_actions(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut level_start: ResMut<LevelStart>,
+    mut settings: ResMut<Settings>,
+    tuning: Res<Tuning>,
+    profiles: Res<Assets<MovementProfiles>>,
+    music: Res<AudioChannel<BackgroundMusic>>,
+    sfx: Res<AudioChannel<SoundEffects>>,
+) {
+    for (interaction, menu_button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match menu_button {
+            MenuButton::Play => {
+                level_start.0 = None;
+                next_game_state.set(GameState::LoadGame);
+            }
+            MenuButton::LevelSelect => next_menu_state.set(MenuState::LevelSelect),
+            MenuButton::Level(level_iid) => {
+                level_start.0 = Some(LevelIid::new(level_iid.clone()));
+                next_game_state.set(GameState::LoadGame);
+            }
+            MenuButton::Options => next_menu_state.set(MenuState::Options),
+            MenuButton::IsolatedLevels => {
+                settings.isolated_levels = !settings.isolated_levels;
+                settings.save();
+            }
+            MenuButton::MovementProfile => {
+                let Some(profiles) = profiles.get(&tuning.movement) else {
+                    continue;
+                };
+                let names = profiles.names();
+                let next = names
+                    .iter()
+                    .position(|name| *name == settings.movement_profile)
+                    .map_or(0, |i| (i + 1) % names.len());
+                if let Some(name) = names.get(next) {
+                    settings.movement_profile = name.clone();
+                    settings.save();
+                }
+            }
+            MenuButton::Back => next_menu_state.set(MenuState::Title),
+            MenuButton::Resume => next_pause_state.set(PauseState::Running),
+            MenuButton::QuitToMenu => next_game_state.set(GameState::Menu),
+            MenuButton::MusicUp
+            | MenuButton::MusicDown
+            | MenuButton::SfxUp
+            | MenuButton::SfxDown => {
+                let step = match menu_button {
+                    MenuButton::MusicUp | MenuButton::SfxUp => 0.05,
+                    _ => -0.05,
+                };
+                let volume = match menu_button {
+                    MenuButton::MusicUp | MenuButton::MusicDown => &mut settings.music_volume,
+                    _ => &mut settings.sfx_volume,
+                };
+                *volume = (*volume + step).clamp(0.0, 1.0);
+                music.set_volume(settings.music_volume);
+                sfx.set_volume(settings.sfx_volume);
+                settings.save();
+            }
+        }
+    }
+}
+
+pub fn apply_audio_settings(
+    settings: Res<Settings>,
+    music: Res<AudioChannel<BackgroundMusic>>,
+    sfx: Res<AudioChannel<SoundEffects>>,
+) {
+    music.set_volume(settings.music_volume);
+    sfx.set_volume(settings.sfx_volume);
+}
+
+pub fn toggle_pause(
+    action_state: Res<ActionState>,
+    state: Option<Res<State<PauseState>>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let Some(state) = state else {
+        return;
+    };
+    if action_state.just_pressed(Action::Pause) {
+        next_pause_state.set(match state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
+}
+
+pub fn pause_game(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn resume_game(mut time: ResMut<Time<Virtual>>, mut player_input: ResMut<PlayerInput>) {
+    time.unpause();
+    // Don't let the jump that was pressed in the menu carry over.
+    player_input.jump_pressed = false;
+}
@@ -0,0 +1,350 @@
+use crate::assets::Tuning;
+use crate::camera::CameraFollow;
+use crate::entities::player::PlayerMarker;
+use crate::menu::Settings;
+use crate::player_movement::{Crouch, Distance, Jump, Run, WallJump};
+use crate::ron_asset::RonAssetLoader;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::assets::{LdtkProject, LevelMetadataAccessor};
+use bevy_ecs_ldtk::prelude::LdtkFields;
+use bevy_ecs_ldtk::LevelSelection;
+use bevy_rapier2d::prelude::{
+    CharacterAutostep, CharacterLength, KinematicCharacterController, RapierConfiguration,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct MovementProfilePlugin;
+impl Plugin for MovementProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<MovementProfiles>();
+        app.register_asset_loader(RonAssetLoader::<MovementProfiles>::new(&["profiles.ron"]));
+        app.init_resource::<ActiveMovementProfile>();
+        app.add_systems(
+            Update,
+            apply_movement_profile.run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+pub const DEFAULT_GRAVITY: f32 = -300.0;
+
+/// Movement tuning, copied onto the player's `Jump`, `Run` and character controller, the camera and the
+/// physics gravity.
+/// Leaving a field out of the file keeps the built in default.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MovementProfile {
+    pub gravity: f32, // world gravity for crates and such, the player's comes from the jump settings
+    pub jump_apex_height: Distance,
+    pub jump_time_to_apex: f32,
+    pub jump_min_height: Distance,
+    pub jump_release_cutoff: f32,
+    pub jump_fall_gravity_multiplier: f32,
+    pub jump_terminal_velocity: Distance,
+    pub jump_coyote_time: f32,
+    pub jump_apex_hang_speed: Distance,
+    pub jump_apex_hang_gravity: f32,
+    pub run_base_speed: Distance,
+    pub run_max_speed: Distance,
+    pub run_time_for_max_speed: f32,
+    pub run_speed_curve: f32,
+    pub run_acceleration: Distance,
+    pub run_deceleration: Distance,
+    pub run_skid_deceleration: Distance,
+    pub run_skid_speed: Distance,
+    pub run_ground_control: f32,
+    pub run_air_control: f32,
+    pub run_air_deceleration: Distance,
+    pub wall_slide_speed: Distance,
+    pub wall_jump_angle: f32,
+    pub wall_jump_lockout: f32,
+    pub crouch_height: f32,
+    pub crouch_walk_speed: Distance,
+    pub crouch_slide_speed: Distance,
+    pub crouch_slide_deceleration: Distance,
+    pub controller: ControllerTuning,
+    pub camera: CameraFollow,
+}
+impl Default for MovementProfile {
+    fn default() -> Self {
+        Self::from_components(
+            &Jump::default(),
+            &Run::default(),
+            &WallJump::default(),
+            &Crouch::default(),
+            ControllerTuning::default(),
+            CameraFollow::default(),
+            DEFAULT_GRAVITY,
+        )
+    }
+}
+impl MovementProfile {
+    pub fn from_components(
+        jump: &Jump,
+        run: &Run,
+        wall_jump: &WallJump,
+        crouch: &Crouch,
+        controller: ControllerTuning,
+        camera: CameraFollow,
+        gravity: f32,
+    ) -> Self {
+        Self {
+            gravity,
+            jump_apex_height: jump.apex_height.clone(),
+            jump_time_to_apex: jump.time_to_apex,
+            jump_min_height: jump.min_height.clone(),
+            jump_release_cutoff: jump.release_cutoff,
+            jump_fall_gravity_multiplier: jump.fall_gravity_multiplier,
+            jump_terminal_velocity: jump.terminal_velocity.clone(),
+            jump_coyote_time: jump.coyote_time,
+            jump_apex_hang_speed: jump.apex_hang_speed.clone(),
+            jump_apex_hang_gravity: jump.apex_hang_gravity,
+            run_base_speed: run.base_speed.clone(),
+            run_max_speed: run.max_speed.clone(),
+            run_time_for_max_speed: run.time_for_max_speed,
+            run_speed_curve: run.speed_curve,
+            run_acceleration: run.acceleration.clone(),
+            run_deceleration: run.deceleration.clone(),
+            run_skid_deceleration: run.skid_deceleration.clone(),
+            run_skid_speed: run.skid_speed.clone(),
+            run_ground_control: run.ground_control,
+            run_air_control: run.air_control,
+            run_air_deceleration: run.air_deceleration.clone(),
+            wall_slide_speed: wall_jump.slide_speed.clone(),
+            wall_jump_angle: wall_jump.angle,
+            wall_jump_lockout: wall_jump.lockout,
+            crouch_height: crouch.height,
+            crouch_walk_speed: crouch.walk_speed.clone(),
+            crouch_slide_speed: crouch.slide_speed.clone(),
+            crouch_slide_deceleration: crouch.slide_deceleration.clone(),
+            controller,
+            camera,
+        }
+    }
+    /// Only touches the tuning, a jump or run in progress carries on.
+    pub fn apply(
+        &self,
+        jump: &mut Jump,
+        run: &mut Run,
+        wall_jump: &mut WallJump,
+        crouch: &mut Crouch,
+        controller: &mut KinematicCharacterController,
+    ) {
+        jump.apex_height = self.jump_apex_height.clone();
+        jump.time_to_apex = self.jump_time_to_apex;
+        jump.min_height = self.jump_min_height.clone();
+        jump.release_cutoff = self.jump_release_cutoff;
+        jump.fall_gravity_multiplier = self.jump_fall_gravity_multiplier;
+        jump.terminal_velocity = self.jump_terminal_velocity.clone();
+        jump.coyote_time = self.jump_coyote_time;
+        jump.apex_hang_speed = self.jump_apex_hang_speed.clone();
+        jump.apex_hang_gravity = self.jump_apex_hang_gravity;
+        run.base_speed = self.run_base_speed.clone();
+        run.max_speed = self.run_max_speed.clone();
+        run.time_for_max_speed = self.run_time_for_max_speed;
+        run.speed_curve = self.run_speed_curve;
+        run.acceleration = self.run_acceleration.clone();
+        run.deceleration = self.run_deceleration.clone();
+        run.skid_deceleration = self.run_skid_deceleration.clone();
+        run.skid_speed = self.run_skid_speed.clone();
+        run.ground_control = self.run_ground_control;
+        run.air_control = self.run_air_control;
+        run.air_deceleration = self.run_air_deceleration.clone();
+        wall_jump.slide_speed = self.wall_slide_speed.clone();
+        wall_jump.angle = self.wall_jump_angle;
+        wall_jump.lockout = self.wall_jump_lockout;
+        crouch.height = self.crouch_height;
+        crouch.walk_speed = self.crouch_walk_speed.clone();
+        crouch.slide_speed = self.crouch_slide_speed.clone();
+        crouch.slide_deceleration = self.crouch_slide_deceleration.clone();
+        self.controller.apply(controller);
+    }
+}
+
+/// The tunable parts of the player's `KinematicCharacterController`. Lengths relative to the collider
+/// stay relative and absolute ones stay in pixels, angles are in degrees. The filters, shape and `up`
+/// belong to the player rather than the feel, so they're left alone.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ControllerTuning {
+    pub offset: f32, // gap kept from other colliders, relative
+    pub normal_nudge_factor: f32,
+    pub slide: bool,
+    pub autostep: Option<Autostep>,
+    pub max_slope_climb_angle: f32,
+    pub min_slope_slide_angle: f32,
+    pub snap_to_ground: Option<f32>, // pixels
+    pub apply_impulse_to_dynamic_bodies: bool,
+    pub custom_mass: Option<f32>, // mass used to push dynamic bodies, the collider's own if none
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Autostep {
+    pub max_height: f32, // relative
+    pub min_width: f32,  // relative
+    pub include_dynamic_bodies: bool,
+}
+impl Default for Autostep {
+    fn default() -> Self {
+        Self {
+            max_height: 0.25,
+            min_width: 0.5,
+            include_dynamic_bodies: true,
+        }
+    }
+}
+impl Default for ControllerTuning {
+    fn default() -> Self {
+        Self {
+            offset: 0.01,
+            normal_nudge_factor: 0.001,
+            slide: true,
+            autostep: None,
+            max_slope_climb_angle: 46.0,
+            min_slope_slide_angle: 46.0,
+            snap_to_ground: Some(2.0), // enough to follow a 45° slope down at full speed
+            apply_impulse_to_dynamic_bodies: true,
+            custom_mass: None,
+        }
+    }
+}
+impl ControllerTuning {
+    pub fn from_controller(controller: &KinematicCharacterController) -> Self {
+        let length = |length: CharacterLength| match length {
+            CharacterLength::Relative(l) | CharacterLength::Absolute(l) => l,
+        };
+        Self {
+            offset: length(controller.offset),
+            normal_nudge_factor: controller.normal_nudge_factor,
+            slide: controller.slide,
+            autostep: controller.autostep.map(|autostep| Autostep {
+                max_height: length(autostep.max_height),
+                min_width: length(autostep.min_width),
+                include_dynamic_bodies: autostep.include_dynamic_bodies,
+            }),
+            max_slope_climb_angle: controller.max_slope_climb_angle.to_degrees(),
+            min_slope_slide_angle: controller.min_slope_slide_angle.to_degrees(),
+            snap_to_ground: controller.snap_to_ground.map(length),
+            apply_impulse_to_dynamic_bodies: controller.apply_impulse_to_dynamic_bodies,
+            custom_mass: controller.custom_mass,
+        }
+    }
+    pub fn apply(&self, controller: &mut KinematicCharacterController) {
+        controller.offset = CharacterLength::Relative(self.offset);
+        controller.normal_nudge_factor = self.normal_nudge_factor;
+        controller.slide = self.slide;
+        controller.autostep = self.autostep.as_ref().map(|autostep| CharacterAutostep {
+            max_height: CharacterLength::Relative(autostep.max_height),
+            min_width: CharacterLength::Relative(autostep.min_width),
+            include_dynamic_bodies: autostep.include_dynamic_bodies,
+        });
+        controller.max_slope_climb_angle = self.max_slope_climb_angle.to_radians();
+        controller.min_slope_slide_angle = self.min_slope_slide_angle.to_radians();
+        controller.snap_to_ground = self.snap_to_ground.map(CharacterLength::Absolute);
+        controller.apply_impulse_to_dynamic_bodies = self.apply_impulse_to_dynamic_bodies;
+        controller.custom_mass = self.custom_mass;
+    }
+    pub fn controller(&self) -> KinematicCharacterController {
+        let mut controller = KinematicCharacterController::default();
+        self.apply(&mut controller);
+        controller
+    }
+}
+
+/// Named presets from assets/movement.profiles.ron.
+#[derive(Asset, TypePath, Serialize, Deserialize, Debug, Default)]
+pub struct MovementProfiles(pub HashMap<String, MovementProfile>);
+impl MovementProfiles {
+    pub const DEFAULT: &'static str = "default";
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.0.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Name of the profile in use, from the level's `movement_profile` field or else the options.
+#[derive(Resource, Default, Debug)]
+pub struct ActiveMovementProfile(pub Option<String>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_movement_profile(
+    settings: Res<Settings>,
+    tuning: Res<Tuning>,
+    profiles: Res<Assets<MovementProfiles>>,
+    mut profile_events: EventReader<AssetEvent<MovementProfiles>>,
+    level_selection: Res<LevelSelection>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut active: ResMut<ActiveMovementProfile>,
+    mut player: Query<
+        (
+            &mut Jump,
+            &mut Run,
+            &mut WallJump,
+            &mut Crouch,
+            &mut KinematicCharacterController,
+        ),
+        With<PlayerMarker>,
+    >,
+    new_player: Query<(), Added<PlayerMarker>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut camera_follow: ResMut<CameraFollow>,
+) {
+    let level_override = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+        .and_then(|project| project.find_raw_level_by_level_selection(&level_selection))
+        .and_then(|level| level.get_maybe_string_field("movement_profile").ok())
+        .and_then(|name| name.clone());
+    let name = level_override.unwrap_or_else(|| settings.movement_profile.clone());
+
+    let reloaded = profile_events
+        .read()
+        .any(|event| event.is_modified(&tuning.movement));
+    if active.0.as_ref() == Some(&name) && !reloaded && new_player.is_empty() {
+        return;
+    }
+    let Ok((mut jump, mut run, mut wall_jump, mut crouch, mut controller)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    let Some(profiles) = profiles.get(&tuning.movement) else {
+        return;
+    };
+    let profile = profiles.0.get(&name).cloned().unwrap_or_else(|| {
+        warn!("No movement profile named {:?}, using the defaults.", name);
+        MovementProfile::default()
+    });
+    profile.apply(
+        &mut jump,
+        &mut run,
+        &mut wall_jump,
+        &mut crouch,
+        &mut controller,
+    );
+    rapier_config.gravity.y = profile.gravity;
+    *camera_follow = profile.camera;
+    active.0 = Some(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_profiles_parse() {
+        let profiles: MovementProfiles =
+            ron::de::from_str(include_str!("../assets/movement.profiles.ron")).unwrap();
+        assert_eq!(
+            profiles.names(),
+            vec!["default", "floaty", "speedrun", "tight"]
+        );
+        let default = &profiles.0[MovementProfiles::DEFAULT];
+        assert_eq!(default.gravity, MovementProfile::default().gravity);
+    }
+}
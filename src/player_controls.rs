@@ -1,15 +1,23 @@
-use crate::entities::player::PlayerMarker;
+use crate::animation::AnimationEvent;
+use crate::death::Dying;
+use crate::entities::player::{squash_player_sprite, PlayerMarker};
+use crate::input::{collect_player_input, Action, ActionState, PlayerInput};
+use crate::menu::PauseState;
 use crate::player_movement::{
-    player_wall_ceiling_checks, update_character_position_from_velocity, update_jump_component,
-    update_run_component, update_speedometer, Jump, Run,
+    player_wall_ceiling_checks, update_character_position_from_velocity, update_crouch_component,
+    update_jump_component, update_one_way_platforms, update_run_component, update_speedometer,
+    update_wall_jump_component, Crouch, Jump, Run, SideChecks, WallJump,
 };
-use crate::{GameState, PlaySoundEffect, RustAnimationAtlas};
+use crate::pushing::{update_push_grab, PushGrab};
+use crate::{GameState, PlaySoundEffect, RespawnPlayer, RustAnimationAtlas};
 use bevy::prelude::*;
 use bevy_ecs_ldtk::assets::LdtkProject;
 use bevy_ecs_ldtk::prelude::LevelMetadataAccessor;
 use bevy_ecs_ldtk::{LevelIid, LevelSelection, Respawn};
+use bevy_rapier2d::plugin::PhysicsSet;
 use bevy_rapier2d::render::DebugRenderContext;
 use iyes_perf_ui::prelude::{PerfUiEntryFPS, PerfUiEntryFPSWorst, PerfUiRoot};
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
 use std::time::Duration;
 
@@ -18,16 +26,32 @@ impl Plugin for PlayerControlPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             PreUpdate,
-            (update_player_controls, player_wall_ceiling_checks)
-                .run_if(in_state(GameState::LoadGame)),
+            update_player_controls
+                .after(collect_player_input)
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(in_state(PauseState::Paused))),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                player_wall_ceiling_checks,
+                apply_player_input,
+                update_one_way_platforms,
+                update_jump_component,
+                update_wall_jump_component,
+                update_run_component,
+                update_crouch_component,
+                update_push_grab,
+                update_character_position_from_velocity,
+            )
+                .chain()
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(GameState::InGame)),
         );
         app.add_systems(
             Update,
             (
                 update_speedometer,
-                update_character_position_from_velocity,
-                update_jump_component.before(update_character_position_from_velocity),
-                update_run_component.before(update_character_position_from_velocity),
                 update_player_states,
                 update_player_animation,
             ),
@@ -58,19 +82,19 @@ impl InputBuffer {
     }
 }
 
-#[derive(Component, Copy, Clone, Debug, Default)]
+#[derive(Component, Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PlayerState {
-    animation_state: AnimationState,
-    direction: AnimationDirection,
+    pub animation_state: AnimationState,
+    pub direction: AnimationDirection,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub enum AnimationDirection {
     #[default]
     Left,
     Right,
 }
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub enum AnimationState {
     // platformer, left/right by image flip.
     #[default]
@@ -79,85 +103,85 @@ pub enum AnimationState {
     Running,
     Crouching,
     Jumping,
+    Skidding,
+    WallSliding,
+    Sliding,
     CrouchWalking,
     Pushing,
     Grabbing,
     GrabWalk,
 }
+impl AnimationState {
+    /// Name of the atlas clip played in this state.
+    pub fn clip(self) -> &'static str {
+        match self {
+            AnimationState::Idle => "Idle",
+            AnimationState::Walking => "Walking",
+            AnimationState::Running => "Running",
+            AnimationState::Crouching => "Crouching",
+            AnimationState::Jumping => "Jumping",
+            AnimationState::Skidding => "Skidding",
+            AnimationState::WallSliding => "WallSliding",
+            AnimationState::Sliding => "Sliding",
+            AnimationState::CrouchWalking => "CrouchWalking",
+            AnimationState::Pushing => "Pushing",
+            AnimationState::Grabbing => "Grabbing",
+            AnimationState::GrabWalk => "GrabWalk",
+        }
+    }
+}
 
-pub fn update_player_controls(
+#[allow(clippy::type_complexity)]
+pub fn apply_player_input(
     mut input_buffering: Local<InputBuffer>,
-    mut step_timer: Local<Timer>,
     time: Res<Time>,
-    mut player_components_query: Query<(&PlayerState, &mut Run, &mut Jump), With<PlayerMarker>>,
-    input: Res<ButtonInput<KeyCode>>,
-    mut debug: ResMut<DebugRenderContext>,
+    player_input: Res<PlayerInput>,
+    mut player_components_query: Query<
+        (&mut Run, &mut Jump, &mut WallJump, &mut Crouch, &SideChecks),
+        (With<PlayerMarker>, Without<Dying>),
+    >,
     mut commands: Commands,
-    ui: Query<Entity, With<PerfUiRoot>>,
-    level_selection: Res<LevelSelection>,
-    levels: Query<(Entity, &LevelIid)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
-    let jump_buttons = [KeyCode::KeyW, KeyCode::ArrowUp, KeyCode::Space];
-    let crouch_buttons = [KeyCode::KeyS, KeyCode::ArrowDown];
-    let left_buttons = [KeyCode::KeyA, KeyCode::ArrowLeft];
-    let right_buttons = [KeyCode::KeyD, KeyCode::ArrowRight];
-
-    if step_timer.mode() != TimerMode::Repeating {
-        step_timer.set_mode(TimerMode::Repeating);
-        step_timer.set_duration(Duration::from_secs_f32(0.3));
-    }
-    for (state, mut run, mut jump) in player_components_query.iter_mut() {
+    for (mut run, mut jump, mut wall_jump, mut crouch, sides) in player_components_query.iter_mut()
+    {
         input_buffering.tick(time.delta());
-        if input.any_just_pressed(jump_buttons) {
+        if player_input.jump_pressed {
             input_buffering.reset();
-            if jump.try_jump() {
+            if jump.try_jump() || wall_jump.try_wall_jump(sides.wall(), &mut jump, &mut run) {
                 commands.trigger(PlaySoundEffect::Jump)
             }
         }
-        if input.any_pressed(jump_buttons) {
-            jump.jump_held = true;
-        } else {
-            jump.jump_held = false;
-        }
-        if input.any_pressed(jump_buttons) && input_buffering.can_jump() {
-            if jump.try_jump() {
-                commands.trigger(PlaySoundEffect::Jump)
-            }
+        jump.jump_held = player_input.jump;
+        if player_input.jump
+            && input_buffering.can_jump()
+            && (jump.try_jump() || wall_jump.try_wall_jump(sides.wall(), &mut jump, &mut run))
+        {
+            commands.trigger(PlaySoundEffect::Jump)
         }
-        if input.any_pressed(crouch_buttons) {
-            //Not yet implemented (or decided..)
-        }
-        run.running = match (
-            input.any_pressed(left_buttons),
-            input.any_pressed(right_buttons),
-        ) {
-            (true, false) => Some(-1.0),
-            (false, true) => Some(1.0),
-            _ => None,
-        };
-
-        if input.any_pressed([
-            KeyCode::KeyA,
-            KeyCode::KeyD,
-            KeyCode::ArrowLeft,
-            KeyCode::ArrowRight,
-        ]) {
-            if state.animation_state == AnimationState::Walking {
-                step_timer.tick(time.delta());
-                if step_timer.just_finished() {
-                    commands.trigger(PlaySoundEffect::Walk);
-                }
-            }
+        crouch.held = player_input.crouch;
+        run.running = player_input.direction();
+        if player_input.respawn {
+            commands.trigger(RespawnPlayer);
         }
     }
+}
 
-    if input.just_pressed(KeyCode::Digit2) {
+#[allow(clippy::too_many_arguments)]
+pub fn update_player_controls(
+    action_state: Res<ActionState>,
+    mut debug: ResMut<DebugRenderContext>,
+    mut commands: Commands,
+    ui: Query<Entity, With<PerfUiRoot>>,
+    level_selection: Res<LevelSelection>,
+    levels: Query<(Entity, &LevelIid)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if action_state.just_pressed(Action::ToggleDebug) {
         debug.enabled = !debug.enabled;
     }
 
-    if input.just_pressed(KeyCode::Digit1) {
+    if action_state.just_pressed(Action::ToggleFps) {
         if let Ok(ui) = ui.get_single() {
             commands.entity(ui).despawn_recursive();
         } else {
@@ -173,7 +197,7 @@ pub fn update_player_controls(
         }
     }
 
-    if input.just_pressed(KeyCode::F1) {
+    if action_state.just_pressed(Action::Restart) {
         if let Some(only_project) = ldtk_project_assets.get(ldtk_projects.single()) {
             let level_selection_iid = LevelIid::new(
                 only_project
@@ -192,17 +216,45 @@ pub fn update_player_controls(
     }
 }
 
-pub fn update_player_states(mut state: Query<(&mut PlayerState, &Jump, &Run), With<PlayerMarker>>) {
-    for (mut state, jump, run) in state.iter_mut() {
+/// Footsteps come from "step" events on the walking frames, see assets/player.anim.ron.
+pub fn play_footsteps(trigger: Trigger<AnimationEvent>, mut commands: Commands) {
+    if trigger.event().0 == "step" {
+        commands.trigger(PlaySoundEffect::Walk);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_player_states(
+    mut state: Query<
+        (&mut PlayerState, &Jump, &Run, &WallJump, &Crouch, &PushGrab),
+        With<PlayerMarker>,
+    >,
+) {
+    for (mut state, jump, run, wall_jump, crouch, push_grab) in state.iter_mut() {
         use AnimationDirection::*;
         use AnimationState::*;
-        state.direction = match run.running {
+        // Face away from the wall while sliding down it, and towards a held crate.
+        let facing = wall_jump
+            .sliding
+            .map(|side| -side)
+            .or(push_grab.grabbing.map(|(_, side)| side))
+            .or(run.running);
+        state.direction = match facing {
             Some(-1.0) => Left,
             Some(1.0) => Right,
             _ => state.direction,
         };
 
         state.animation_state = match (jump.jumping, run.running, jump.grounded) {
+            _ if wall_jump.sliding.is_some() => WallSliding,
+            (_, Some(_), _) if push_grab.grabbing.is_some() => GrabWalk,
+            _ if push_grab.grabbing.is_some() => Grabbing,
+            _ if push_grab.pushing.is_some() => Pushing,
+            _ if crouch.sliding => Sliding,
+            (_, Some(_), _) if crouch.crouching => CrouchWalking,
+            _ if crouch.crouching => Crouching,
+            _ if run.skidding => Skidding,
+            (_, Some(_), true) if run.at_full_speed() => Running,
             (_, Some(_), true) => Walking,
             (_, _, false) => Jumping,
             _ => Idle,
@@ -211,18 +263,50 @@ pub fn update_player_states(mut state: Query<(&mut PlayerState, &Jump, &Run), Wi
 }
 
 pub fn update_player_animation(
-    mut player: Query<(&mut Sprite, &PlayerState, &mut RustAnimationAtlas), With<PlayerMarker>>,
+    mut player: Query<
+        (&mut Sprite, &PlayerState, &Crouch, &mut RustAnimationAtlas),
+        With<PlayerMarker>,
+    >,
 ) {
-    for (mut sprite, state, mut animation) in player.iter_mut() {
-        animation.set_current(match state.animation_state {
-            AnimationState::Idle => 0,
-            AnimationState::Walking => 2,
-            AnimationState::Jumping => 4,
-            _ => 0,
-        });
+    for (mut sprite, state, crouch, mut animation) in player.iter_mut() {
+        animation.play(state.animation_state.clip());
         sprite.flip_x = match state.direction {
             AnimationDirection::Left => true,
             AnimationDirection::Right => false,
         };
+        squash_player_sprite(&mut sprite, crouch.current_height());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::AnimationSet;
+
+    #[test]
+    fn every_state_has_a_player_clip() {
+        use AnimationState::*;
+        let set: AnimationSet =
+            ron::de::from_str(include_str!("../assets/player.anim.ron")).unwrap();
+        for state in [
+            Idle,
+            Walking,
+            Running,
+            Crouching,
+            Jumping,
+            Skidding,
+            WallSliding,
+            Sliding,
+            CrouchWalking,
+            Pushing,
+            Grabbing,
+            GrabWalk,
+        ] {
+            assert!(
+                set.clips.iter().any(|clip| clip.name == state.clip()),
+                "{:?}",
+                state
+            );
+        }
     }
 }
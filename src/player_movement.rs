@@ -1,9 +1,12 @@
-use crate::entities::player::PlayerMarker;
+use crate::entities::crates::CrateMarker;
+use crate::entities::platform::MovingPlatform;
+use crate::entities::player::{PlayerMarker, PLAYER_HALF_SIZE};
 use crate::*;
 use bevy::math::Vec2;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Reflect)]
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
 pub enum Distance {
     Meters(f32),
     Pixels(f32),
@@ -68,41 +71,80 @@ pub fn update_speedometer(
     }
 }
 
-#[derive(Reflect, Component, Debug)]
+/// Vertical movement. The rise is a constant gravity arc worked out from `apex_height` and
+/// `time_to_apex`, falling uses a multiple of that gravity.
+#[derive(Reflect, Component, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Jump {
     pub jumping: bool,
-    pub max_distance: Distance,
-    pub min_distance: Distance,
-    pub speed: Distance,
-    pub current_distance: f32,
-    pub velocity: f32,
-    pub grounded: bool,
+    pub apex_height: Distance,        // height of a held jump
+    pub time_to_apex: f32,            // seconds to get there
+    pub min_height: Distance,         // a tapped jump still rises this far before the cutoff
+    pub release_cutoff: f32,          // upward speed is multiplied by this on letting go of jump
+    pub fall_gravity_multiplier: f32, // falling is this much heavier than rising
+    pub terminal_velocity: Distance,  // max fall speed, per second
+    pub coyote_time: f32,             // seconds after walking off a ledge you can still jump
+    pub apex_hang_speed: Distance,    // below this vertical speed, per second, a held jump hangs
+    pub apex_hang_gravity: f32,       // gravity multiplier while hanging
+    pub current_height: f32,          // how far the current jump has risen, 0 until you jump
+    pub velocity: f32,                // pixels per second, output of this component
+    pub grounded: bool,               // set from the side checks
     pub ceiling: bool,
-    pub jump_held: bool,
+    pub jump_held: bool, // set in controls to reference button being held.
+    pub cut: bool,       // release cutoff already applied this jump
+    pub air_time: f32,   // seconds since last grounded, for coyote time
 }
 impl Jump {
+    /// Gravity of the rising part of the jump, in pixels per second squared.
+    pub fn gravity(&self) -> f32 {
+        2.0 * self.apex_height.to_pixels() / self.time_to_apex.powi(2)
+    }
+    /// Upward speed a jump starts with to reach `apex_height` in `time_to_apex`.
+    pub fn initial_velocity(&self) -> f32 {
+        2.0 * self.apex_height.to_pixels() / self.time_to_apex
+    }
+    pub fn can_jump(&self) -> bool {
+        let on_ground = self.grounded || self.air_time <= self.coyote_time;
+        !self.jumping && self.current_height == 0.0 && on_ground && !self.ceiling
+    }
     pub fn try_jump(&mut self) -> bool {
-        if !self.jumping && self.grounded && !self.ceiling {
+        if self.can_jump() {
             self.jumping = true;
+            self.cut = false;
+            self.velocity = self.initial_velocity();
             true
         } else {
             false
         }
     }
+    /// Drop any jump in progress, keeping the tuning.
+    pub fn stop(&mut self) {
+        self.jumping = false;
+        self.cut = false;
+        self.current_height = 0.0;
+        self.velocity = 0.0;
+    }
 }
 impl Default for Jump {
     fn default() -> Self {
         Self {
-            jumping: false,                      // am I actually jumping
-            max_distance: Distance::Meters(4.0), // max  height
-            min_distance: Distance::Meters(2.0), // min height
-            speed: Distance::Meters(10.0),       // max jump speed, also for falling atm.
-            current_distance: 0.0,               // height of jump
-            velocity: 0.0, // output of this component, used with run output to check position for collision and update.
-            grounded: false, // set to reference if on ground by physics component.
+            jumping: false,
+            apex_height: Distance::Meters(4.0),
+            time_to_apex: 0.4,
+            min_height: Distance::Meters(1.5),
+            release_cutoff: 0.5,
+            fall_gravity_multiplier: 1.5,
+            terminal_velocity: Distance::Meters(20.0),
+            coyote_time: 0.1,
+            apex_hang_speed: Distance::Meters(2.0),
+            apex_hang_gravity: 0.5,
+            current_height: 0.0,
+            velocity: 0.0,
+            grounded: false,
             ceiling: false,
-            jump_held: false, // set in controls to reference button being held.
+            jump_held: false,
+            cut: false,
+            air_time: 0.0,
         }
     }
 }
@@ -113,6 +155,33 @@ pub struct SideChecks {
     right_wall: bool,
     ceiling: bool,
     ground: bool,
+    headroom: f32,              // free space above the player, up to HEADROOM_CHECK
+    left_crate: Option<Entity>, // crates count separately from walls, they can be pushed
+    right_crate: Option<Entity>,
+    standing_on: Option<Entity>,
+}
+/// How far up the ceiling check looks, enough to know whether a crouching player can stand.
+const HEADROOM_CHECK: f32 = 16.0;
+impl SideChecks {
+    /// Which side a wall is touching, -1 left or 1 right.
+    pub fn wall(&self) -> Option<f32> {
+        match (self.left_wall, self.right_wall) {
+            (true, false) => Some(-1.0),
+            (false, true) => Some(1.0),
+            _ => None,
+        }
+    }
+    /// The crate right next to the player on `side`, -1 left or 1 right.
+    pub fn crate_on(&self, side: f32) -> Option<Entity> {
+        match side < 0.0 {
+            true => self.left_crate,
+            false => self.right_crate,
+        }
+    }
+    /// Whatever is right under the player's feet.
+    pub fn standing_on(&self) -> Option<Entity> {
+        self.standing_on
+    }
 }
 pub fn player_wall_ceiling_checks(
     mut player_query: Query<
@@ -125,106 +194,172 @@ pub fn player_wall_ceiling_checks(
         ),
         With<PlayerMarker>,
     >,
+    crates: Query<(), With<CrateMarker>>,
     physics: Res<RapierContext>,
 ) {
     if let Ok((player, collider, transform, mut sides, output)) = player_query.get_single_mut() {
         sides.ground = output.grounded;
         let filter = QueryFilter::new()
             .exclude_collider(player)
-            .exclude_sensors();
-        if let Some((_, _)) = physics.cast_shape(
-            transform.translation.xy(),
-            0.0,
-            vec2(-1.0, 0.0),
-            collider,
-            ShapeCastOptions {
-                max_time_of_impact: 1.0,
-                target_distance: 0.0,
-                stop_at_penetration: true,
-                compute_impact_geometry_on_penetration: false,
-            },
-            filter,
-        ) {
-            sides.left_wall = true;
-        } else {
-            sides.left_wall = false;
-        }
+            .exclude_sensors()
+            .groups(CollisionGroups::new(PLAYER_GROUP, Group::ALL));
+        let side_hit = |direction: Vec2| {
+            physics
+                .cast_shape(
+                    transform.translation.xy(),
+                    0.0,
+                    direction,
+                    collider,
+                    ShapeCastOptions {
+                        max_time_of_impact: 1.0,
+                        target_distance: 0.0,
+                        stop_at_penetration: true,
+                        compute_impact_geometry_on_penetration: false,
+                    },
+                    filter,
+                )
+                .map(|(entity, _)| entity)
+        };
+        let left = side_hit(vec2(-1.0, 0.0));
+        sides.left_crate = left.filter(|entity| crates.contains(*entity));
+        sides.left_wall = left.is_some() && sides.left_crate.is_none();
 
-        if let Some((_, _)) = physics.cast_shape(
-            transform.translation.xy(),
-            0.0,
-            vec2(1.0, 0.0),
-            collider,
-            ShapeCastOptions {
-                max_time_of_impact: 1.0,
-                target_distance: 0.0,
-                stop_at_penetration: true,
-                compute_impact_geometry_on_penetration: false,
-            },
-            filter,
-        ) {
-            sides.right_wall = true;
-        } else {
-            sides.right_wall = false;
-        }
+        let right = side_hit(vec2(1.0, 0.0));
+        sides.right_crate = right.filter(|entity| crates.contains(*entity));
+        sides.right_wall = right.is_some() && sides.right_crate.is_none();
 
-        if let Some((_, _)) = physics.cast_shape(
-            transform.translation.xy(),
-            0.0,
-            vec2(0.0, 1.0),
-            collider,
-            ShapeCastOptions {
-                max_time_of_impact: 1.0,
-                target_distance: 0.0,
-                stop_at_penetration: true,
-                compute_impact_geometry_on_penetration: false,
+        sides.standing_on = side_hit(vec2(0.0, -1.0));
+
+        sides.headroom = physics
+            .cast_shape(
+                transform.translation.xy(),
+                0.0,
+                vec2(0.0, 1.0),
+                collider,
+                ShapeCastOptions {
+                    max_time_of_impact: HEADROOM_CHECK,
+                    target_distance: 0.0,
+                    stop_at_penetration: true,
+                    compute_impact_geometry_on_penetration: false,
+                },
+                QueryFilter::exclude_dynamic()
+                    .exclude_sensors()
+                    .exclude_collider(player)
+                    .groups(CollisionGroups::new(PLAYER_GROUP, Group::ALL)),
+            )
+            .map_or(HEADROOM_CHECK, |(_, hit)| hit.time_of_impact);
+        sides.ceiling = sides.headroom <= 1.0;
+    }
+}
+/// Collision group of the player, one way platforms drop it from their filter to let the player through.
+pub const PLAYER_GROUP: Group = Group::GROUP_2;
+
+/// Thin platform that only blocks the player from above, tagged `one_way` in the tileset.
+#[derive(Component, Default)]
+pub struct OneWayPlatform;
+
+pub fn update_one_way_platforms(
+    player_input: Res<PlayerInput>,
+    player: Query<(&GlobalTransform, &Collider, &Jump), With<PlayerMarker>>,
+    mut platforms: Query<(&GlobalTransform, &Collider, &mut CollisionGroups), With<OneWayPlatform>>,
+) {
+    let Ok((player_transform, player_collider, jump)) = player.get_single() else {
+        return;
+    };
+    let half_height = |collider: &Collider| {
+        collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents().y)
+            .unwrap_or_default()
+    };
+    // The crouching collider is offset, so go by its bounds.
+    let feet = player_transform.translation().y + player_collider.raw.compute_local_aabb().mins.y;
+    for (transform, collider, mut groups) in platforms.iter_mut() {
+        let top = transform.translation().y + half_height(collider);
+        // Solid once we're standing on or falling onto it, passable going up or holding Crouch.
+        let solid = feet >= top - 0.5 && !jump.jumping && !player_input.crouch;
+        groups.set_if_neq(CollisionGroups::new(
+            Group::ALL,
+            match solid {
+                true => Group::ALL,
+                false => !PLAYER_GROUP,
             },
-            QueryFilter::exclude_dynamic()
-                .exclude_sensors()
-                .exclude_collider(player),
-        ) {
-            sides.ceiling = true;
-        } else {
-            sides.ceiling = false;
-        }
+        ));
     }
 }
+
 pub fn update_jump_component(mut entities: Query<(&mut Jump, &SideChecks)>, time: Res<Time>) {
-    for (mut jump, output) in entities.iter_mut() {
-        jump.grounded = output.ground;
-        jump.ceiling = output.ceiling;
+    let dt = time.delta_seconds();
+    for (mut jump, sides) in entities.iter_mut() {
+        jump.grounded = sides.ground;
+        jump.ceiling = sides.ceiling;
+        jump.air_time = if jump.grounded {
+            0.0
+        } else {
+            jump.air_time + dt
+        };
 
-        let max_complete = (jump.current_distance / jump.max_distance.to_pixels()).min(1.0);
-        let min_complete = (jump.current_distance / jump.min_distance.to_pixels()).min(1.0);
-        if output.ceiling {
-            jump.jumping = false;
+        if jump.ceiling && jump.velocity > 0.0 {
+            jump.velocity = 0.0;
         }
-        if (jump.jumping && jump.jump_held && max_complete < 1.0)
-            || (jump.jumping && min_complete < 1.0)
+        // Let go early and the rest of the rise is cut short, once past the minimum height.
+        if jump.jumping
+            && jump.velocity > 0.0
+            && !jump.jump_held
+            && !jump.cut
+            && jump.current_height >= jump.min_height.to_pixels()
         {
-            let jump_speed = jump.speed.to_pixels() * time.delta_seconds();
-            jump.velocity = jump_speed;
-            jump.current_distance += jump_speed;
-        } else if !jump.grounded {
-            jump.jumping = false;
-            let fall_speed = jump.speed.to_pixels() * time.delta_seconds();
-            jump.velocity = -fall_speed;
-        } else if jump.grounded {
+            jump.velocity *= jump.release_cutoff;
+            jump.cut = true;
+        }
+
+        let mut gravity = jump.gravity();
+        if jump.velocity <= 0.0 {
+            gravity *= jump.fall_gravity_multiplier;
+        }
+        let hanging = jump.current_height > 0.0
+            && jump.jump_held
+            && !jump.cut
+            && jump.velocity.abs() < jump.apex_hang_speed.to_pixels();
+        if hanging {
+            gravity *= jump.apex_hang_gravity;
+        }
+        jump.velocity = (jump.velocity - gravity * dt).max(-jump.terminal_velocity.to_pixels());
+
+        if jump.velocity > 0.0 {
+            jump.current_height += jump.velocity * dt;
+        } else {
             jump.jumping = false;
-            jump.current_distance = 0.0;
+            if jump.grounded {
+                // Landed, keep pressing down just enough to stay grounded.
+                jump.current_height = 0.0;
+                jump.velocity = -gravity * dt;
+            }
         }
     }
 }
 
-#[derive(Reflect, Component, Debug)]
+/// Horizontal movement. Holding a direction ramps the target speed from `base_speed` to `max_speed`, and
+/// the velocity chases that target at the acceleration rates below.
+#[derive(Reflect, Component, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Run {
-    pub base_speed: Distance,    // start running at this speed
-    pub max_speed: Distance,     // end up running at this speed
-    pub time_for_max_speed: f32, // after this many seconds
+    pub base_speed: Distance,        // start running at this speed
+    pub max_speed: Distance,         // end up running at this speed
+    pub time_for_max_speed: f32,     // after this many seconds
+    pub speed_curve: f32, // shape of that ramp, 1 is linear, higher is slower to build up
+    pub acceleration: Distance, // per second squared, speeding up
+    pub deceleration: Distance, // per second squared, letting go on the ground
+    pub skid_deceleration: Distance, // per second squared, pushing against your momentum on the ground
+    pub skid_speed: Distance,        // turning around faster than this skids
+    pub ground_control: f32,         // scales acceleration and deceleration on the ground
+    pub air_control: f32,            // scales acceleration and turning in the air
+    pub air_deceleration: Distance, // per second squared, letting go in the air, low keeps momentum
     pub running: Option<f32>, // set this negative 1 left, positive 1 right, none idle. other values will adjust speed.
     pub current_run_time: f32, // how long you've been running.
-    pub velocity: f32,        // this is used to set x movement requested in physics controller
+    pub velocity: f32, // pixels per second, used to set x movement requested in physics controller
+    pub grounded: bool,
+    pub skidding: bool,
 }
 
 impl Default for Run {
@@ -233,32 +368,478 @@ impl Default for Run {
             base_speed: Distance::meters(5.0),
             max_speed: Distance::meters(10.0),
             time_for_max_speed: 2.0,
+            speed_curve: 1.0,
+            acceleration: Distance::meters(40.0),
+            deceleration: Distance::meters(50.0),
+            skid_deceleration: Distance::meters(80.0),
+            skid_speed: Distance::meters(4.0),
+            ground_control: 1.0,
+            air_control: 0.65,
+            air_deceleration: Distance::meters(8.0),
             running: None,
             current_run_time: 0.0,
             velocity: 0.0,
+            grounded: false,
+            skidding: false,
         }
     }
 }
-
-pub fn update_run_component(mut run_query: Query<&mut Run>, time: Res<Time>) {
-    for mut run in run_query.iter_mut() {
-        if let Some(dir) = run.running {
-            run.current_run_time += time.delta_seconds();
-            let current_speed = run.base_speed.to_pixels()
-                + (run.max_speed.to_pixels() - run.base_speed.to_pixels())
-                    * (run.current_run_time / run.time_for_max_speed).min(1.0);
-            run.velocity = current_speed * time.delta_seconds() * dir;
+impl Run {
+    pub fn stop(&mut self) {
+        self.running = None;
+        self.current_run_time = 0.0;
+        self.velocity = 0.0;
+        self.skidding = false;
+    }
+    /// Speed to run at after holding a direction for `current_run_time`.
+    pub fn target_speed(&self) -> f32 {
+        let ramp = (self.current_run_time / self.time_for_max_speed)
+            .clamp(0.0, 1.0)
+            .powf(self.speed_curve);
+        self.base_speed.to_pixels()
+            + (self.max_speed.to_pixels() - self.base_speed.to_pixels()) * ramp
+    }
+    /// Done building up speed, or going faster still.
+    pub fn at_full_speed(&self) -> bool {
+        self.velocity.abs() >= self.max_speed.to_pixels()
+    }
+    /// One step of the velocity towards what the input asks for.
+    pub fn step(&mut self, dt: f32) {
+        let control = match self.grounded {
+            true => self.ground_control,
+            false => self.air_control,
+        };
+        self.skidding = false;
+        let Some(dir) = self.running else {
+            self.current_run_time = 0.0;
+            let rate = match self.grounded {
+                true => self.deceleration.to_pixels() * self.ground_control,
+                false => self.air_deceleration.to_pixels(),
+            };
+            self.velocity = approach(self.velocity, 0.0, rate * dt);
+            return;
+        };
+        let turning = self.velocity * dir < 0.0;
+        if turning {
+            self.current_run_time = 0.0;
+            self.skidding = self.grounded && self.velocity.abs() > self.skid_speed.to_pixels();
         } else {
-            run.current_run_time = 0.0;
+            self.current_run_time += dt;
+        }
+        let target = self.target_speed() * dir;
+        let rate = if self.skidding {
+            self.skid_deceleration.to_pixels() * control
+        } else if self.velocity.abs() <= target.abs() || turning {
+            self.acceleration.to_pixels() * control
+        } else if self.grounded {
+            self.deceleration.to_pixels() * control
+        } else {
+            0.0 // faster than running speed in the air, keep the momentum from the ground
+        };
+        self.velocity = approach(self.velocity, target, rate * dt);
+    }
+}
+
+fn approach(current: f32, target: f32, max_delta: f32) -> f32 {
+    current + (target - current).clamp(-max_delta, max_delta)
+}
+
+pub fn update_run_component(mut run_query: Query<(&mut Run, &SideChecks)>, time: Res<Time>) {
+    for (mut run, sides) in run_query.iter_mut() {
+        run.grounded = sides.ground;
+        run.step(time.delta_seconds());
+        // Don't keep building speed against a wall.
+        if (sides.left_wall && run.velocity < 0.0) || (sides.right_wall && run.velocity > 0.0) {
             run.velocity = 0.0;
         }
     }
 }
 
+/// Sliding down walls you press into, and jumping off them.
+#[derive(Reflect, Component, Debug, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct WallJump {
+    pub slide_speed: Distance, // max fall speed while sliding, per second
+    pub angle: f32,            // degrees above horizontal the wall jump launches at
+    pub lockout: f32,          // seconds horizontal input is ignored after a wall jump
+    pub sliding: Option<f32>,  // wall side, -1 left or 1 right
+    pub lockout_left: f32,
+    pub away: f32, // direction of the last wall jump
+}
+impl Default for WallJump {
+    fn default() -> Self {
+        Self {
+            slide_speed: Distance::meters(4.0),
+            angle: 60.0,
+            lockout: 0.15,
+            sliding: None,
+            lockout_left: 0.0,
+            away: 0.0,
+        }
+    }
+}
+impl WallJump {
+    /// Jumps off the wall on `side`, as high as a normal jump and pushed away by the angle.
+    pub fn try_wall_jump(&mut self, side: Option<f32>, jump: &mut Jump, run: &mut Run) -> bool {
+        let Some(side) = side else {
+            return false;
+        };
+        if jump.grounded || jump.ceiling || self.lockout_left > 0.0 {
+            return false;
+        }
+        let up = jump.initial_velocity();
+        jump.jumping = true;
+        jump.cut = false;
+        jump.velocity = up;
+        self.away = -side;
+        self.lockout_left = self.lockout;
+        self.sliding = None;
+        run.current_run_time = 0.0;
+        run.velocity = self.away * up / self.angle.to_radians().tan().max(0.01);
+        true
+    }
+}
+
+/// Runs between the jump and run updates, so it can cap the fall and override input during the lockout.
+pub fn update_wall_jump_component(
+    mut entities: Query<(&mut WallJump, &mut Jump, &mut Run, &SideChecks)>,
+    time: Res<Time>,
+) {
+    for (mut wall_jump, mut jump, mut run, sides) in entities.iter_mut() {
+        if wall_jump.lockout_left > 0.0 {
+            wall_jump.lockout_left -= time.delta_seconds();
+            run.running = Some(wall_jump.away);
+        }
+        let pressing = sides.wall().filter(|side| run.running == Some(*side));
+        wall_jump.sliding = pressing.filter(|_| !jump.grounded && jump.velocity <= 0.0);
+        if wall_jump.sliding.is_some() {
+            jump.velocity = jump.velocity.max(-wall_jump.slide_speed.to_pixels());
+        }
+    }
+}
+
+/// Crouching shrinks the collider to `height` of standing, running fast into a crouch slides instead.
+#[derive(Reflect, Component, Debug, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Crouch {
+    pub height: f32,                  // fraction of the standing height
+    pub walk_speed: Distance,         // max speed crouch walking, per second
+    pub slide_speed: Distance,        // crouching faster than this starts a slide, per second
+    pub slide_deceleration: Distance, // per second squared
+    pub held: bool,                   // set in controls from the crouch button
+    pub crouching: bool,
+    pub sliding: bool,
+    pub slide_velocity: f32,
+}
+impl Default for Crouch {
+    fn default() -> Self {
+        Self {
+            height: 0.5,
+            walk_speed: Distance::meters(3.0),
+            slide_speed: Distance::meters(7.0),
+            slide_deceleration: Distance::meters(12.0),
+            held: false,
+            crouching: false,
+            sliding: false,
+            slide_velocity: 0.0,
+        }
+    }
+}
+impl Crouch {
+    /// Fraction of the standing height the player is at right now.
+    pub fn current_height(&self) -> f32 {
+        match self.crouching {
+            true => self.height,
+            false => 1.0,
+        }
+    }
+    /// The player's collider, shrunk from the top while crouching so the feet stay put.
+    pub fn collider(&self, half_size: Vec2) -> Collider {
+        if !self.crouching {
+            return Collider::cuboid(half_size.x, half_size.y);
+        }
+        let half_height = half_size.y * self.height;
+        Collider::compound(vec![(
+            vec2(0.0, half_height - half_size.y),
+            0.0,
+            Collider::cuboid(half_size.x, half_height),
+        )])
+    }
+}
+
+/// Runs after the run update, a slide or crouch walk overrides the speed it came up with.
+pub fn update_crouch_component(
+    mut entities: Query<
+        (&mut Crouch, &mut Run, &mut Collider, &Jump, &SideChecks),
+        With<PlayerMarker>,
+    >,
+    time: Res<Time>,
+) {
+    for (mut crouch, mut run, mut collider, jump, sides) in entities.iter_mut() {
+        let was_crouching = crouch.crouching;
+        if crouch.held && jump.grounded && !crouch.crouching {
+            crouch.crouching = true;
+            crouch.sliding = run.velocity.abs() >= crouch.slide_speed.to_pixels();
+            crouch.slide_velocity = run.velocity;
+        }
+        // Standing back up takes the height the crouch took away.
+        let standing_room = 2.0 * PLAYER_HALF_SIZE.y * (1.0 - crouch.height);
+        if crouch.crouching && (!crouch.held || jump.jumping) && sides.headroom >= standing_room {
+            crouch.crouching = false;
+            crouch.sliding = false;
+        }
+
+        if crouch.sliding {
+            crouch.slide_velocity = approach(
+                crouch.slide_velocity,
+                0.0,
+                crouch.slide_deceleration.to_pixels() * time.delta_seconds(),
+            );
+            if sides
+                .wall()
+                .is_some_and(|side| side * crouch.slide_velocity > 0.0)
+            {
+                crouch.slide_velocity = 0.0;
+            }
+            crouch.sliding = crouch.slide_velocity.abs() > crouch.walk_speed.to_pixels();
+            run.velocity = crouch.slide_velocity;
+            run.current_run_time = 0.0;
+        } else if crouch.crouching {
+            let walk_speed = crouch.walk_speed.to_pixels();
+            run.velocity = run.velocity.clamp(-walk_speed, walk_speed);
+            run.current_run_time = 0.0;
+        }
+
+        if crouch.crouching != was_crouching {
+            *collider = crouch.collider(PLAYER_HALF_SIZE);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn update_character_position_from_velocity(
-    mut player_query: Query<(&mut KinematicCharacterController, &Jump, &Run), With<PlayerMarker>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut KinematicCharacterController,
+            &mut Transform,
+            &Collider,
+            &Jump,
+            &Run,
+            &SideChecks,
+        ),
+        With<PlayerMarker>,
+    >,
+    platforms: Query<&MovingPlatform>,
+    physics: Res<RapierContext>,
+    time: Res<Time>,
 ) {
-    for (mut controller, jump, run) in player_query.iter_mut() {
-        controller.translation = Some(Vec2::new(run.velocity, jump.velocity));
+    for (player, mut controller, mut transform, collider, jump, run, sides) in
+        player_query.iter_mut()
+    {
+        // Ride along with whatever platform we're standing on. Rapier only sees the platform's
+        // new position after this step, so the controller would check the ride against where it
+        // was. Move with it directly instead, stopping at walls, and leave the controller our own
+        // motion relative to it.
+        let carrying = match jump.jumping {
+            true => None,
+            false => sides
+                .standing_on()
+                .and_then(|entity| Some((entity, platforms.get(entity).ok()?.delta))),
+        };
+        if let Some((platform, delta)) = carrying.filter(|(_, delta)| *delta != Vec2::ZERO) {
+            let filter = QueryFilter::exclude_dynamic()
+                .exclude_sensors()
+                .exclude_collider(player)
+                .exclude_collider(platform)
+                .groups(CollisionGroups::new(PLAYER_GROUP, Group::ALL));
+            let moved = physics
+                .cast_shape(
+                    transform.translation.xy(),
+                    0.0,
+                    delta,
+                    collider,
+                    ShapeCastOptions {
+                        max_time_of_impact: 1.0,
+                        target_distance: 0.0,
+                        stop_at_penetration: false,
+                        compute_impact_geometry_on_penetration: false,
+                    },
+                    filter,
+                )
+                .map_or(1.0, |(_, hit)| hit.time_of_impact);
+            transform.translation += (delta * moved).extend(0.0);
+        }
+        controller.translation =
+            Some(Vec2::new(run.velocity, jump.velocity) * time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_reaches_apex_height_in_time_to_apex() {
+        let jump = Jump::default();
+        let (v, g) = (jump.initial_velocity(), jump.gravity());
+        assert!((v / g - jump.time_to_apex).abs() < 1e-5);
+        assert!((v * v / (2.0 * g) - jump.apex_height.to_pixels()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn coyote_time_allows_a_late_jump() {
+        let mut jump = Jump {
+            air_time: 0.05,
+            ..default()
+        };
+        assert!(jump.try_jump());
+        assert!(!jump.try_jump());
+        jump.stop();
+        jump.air_time = jump.coyote_time + 0.01;
+        assert!(!jump.try_jump());
+    }
+
+    fn run_for(run: &mut Run, seconds: f32) {
+        for _ in 0..(seconds * 120.0) as usize {
+            run.step(1.0 / 120.0);
+        }
+    }
+
+    #[test]
+    fn run_accelerates_to_max_speed_and_decelerates_to_a_stop() {
+        let mut run = Run {
+            grounded: true,
+            running: Some(1.0),
+            ..default()
+        };
+        let ramp_time = run.time_for_max_speed;
+        run_for(&mut run, ramp_time + 0.5);
+        assert!((run.velocity - run.max_speed.to_pixels()).abs() < 1e-3);
+        run.running = None;
+        run_for(&mut run, 1.0);
+        assert_eq!(run.velocity, 0.0);
+    }
+
+    #[test]
+    fn turning_around_fast_skids() {
+        let mut run = Run {
+            grounded: true,
+            running: Some(1.0),
+            ..default()
+        };
+        run_for(&mut run, 1.0);
+        run.running = Some(-1.0);
+        run.step(1.0 / 120.0);
+        assert!(run.skidding);
+        run_for(&mut run, 1.0);
+        assert!(!run.skidding && run.velocity < 0.0);
+    }
+
+    #[test]
+    fn wall_jump_pushes_away_at_the_angle() {
+        let (mut wall_jump, mut jump, mut run) =
+            (WallJump::default(), Jump::default(), Run::default());
+        assert!(!wall_jump.try_wall_jump(None, &mut jump, &mut run));
+        assert!(wall_jump.try_wall_jump(Some(1.0), &mut jump, &mut run));
+        assert!(run.velocity < 0.0);
+        let angle = (jump.velocity / -run.velocity).atan().to_degrees();
+        assert!((angle - wall_jump.angle).abs() < 1e-3);
+        assert!(!wall_jump.try_wall_jump(Some(1.0), &mut jump, &mut run));
+    }
+
+    #[test]
+    fn crouched_collider_keeps_the_feet_in_place() {
+        let mut crouch = Crouch::default();
+        let standing = crouch.collider(PLAYER_HALF_SIZE).raw.compute_local_aabb();
+        crouch.crouching = true;
+        let crouched = crouch.collider(PLAYER_HALF_SIZE).raw.compute_local_aabb();
+        assert_eq!(crouched.mins, standing.mins);
+        assert!((crouched.maxs.y - crouched.mins.y - PLAYER_HALF_SIZE.y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn air_keeps_momentum_above_running_speed() {
+        let mut run = Run {
+            grounded: false,
+            running: Some(1.0),
+            velocity: Distance::meters(15.0).to_pixels(),
+            ..default()
+        };
+        run_for(&mut run, 0.5);
+        assert_eq!(run.velocity, Distance::meters(15.0).to_pixels());
+    }
+
+    #[test]
+    fn rising_platform_carries_the_player() {
+        use crate::animation::{Easing, Spline, SplineKind};
+        use crate::entities::platform::{move_platforms, MovingPlatform, PathMode};
+        use crate::entities::player::PlayerBundle;
+        use bevy::time::TimeUpdateStrategy;
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(Distance::PIXELS_PER_METER)
+                .in_fixed_schedule(),
+        ));
+        // One fixed tick per update.
+        let dt = Duration::from_secs_f64(1.0 / PHYSICS_HZ);
+        app.insert_resource(Time::<Fixed>::from_duration(dt));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(dt));
+        app.world_mut()
+            .resource_mut::<RapierConfiguration>()
+            .timestep_mode = TimestepMode::Fixed {
+            dt: dt.as_secs_f32(),
+            substeps: 4,
+        };
+        app.add_systems(
+            FixedUpdate,
+            (
+                move_platforms,
+                player_wall_ceiling_checks,
+                update_jump_component,
+                update_character_position_from_velocity,
+            )
+                .chain()
+                .before(PhysicsSet::SyncBackend),
+        );
+        let platform = app
+            .world_mut()
+            .spawn((
+                MovingPlatform::new(PathMode::PingPong, Easing::Linear, 2.0),
+                Spline::new([Vec2::ZERO, vec2(0.0, 64.0)], SplineKind::CatmullRom),
+                TransformBundle::default(),
+                Collider::cuboid(24.0, 4.0),
+                RigidBody::KinematicPositionBased,
+            ))
+            .id();
+        let player = app
+            .world_mut()
+            .spawn(PlayerBundle::default())
+            .insert(TransformBundle::from_transform(Transform::from_xyz(
+                0.0,
+                4.0 + PLAYER_HALF_SIZE.y + 0.5,
+                0.0,
+            )))
+            .id();
+
+        // Still on the way up after 200 ticks.
+        for tick in 0..200 {
+            app.update();
+            let world = app.world();
+            if tick < 10 {
+                continue; // landing
+            }
+            let platform_top = world.get::<Transform>(platform).unwrap().translation.y + 4.0;
+            let feet = world.get::<Transform>(player).unwrap().translation.y - PLAYER_HALF_SIZE.y;
+            assert!(
+                (feet - platform_top).abs() < 0.5,
+                "tick {tick}: feet at {feet}, platform top at {platform_top}"
+            );
+            assert!(world.get::<Jump>(player).unwrap().grounded, "tick {tick}");
+        }
     }
 }
@@ -0,0 +1,124 @@
+use crate::death::Dying;
+use crate::entities::crates::CrateMarker;
+use crate::entities::player::PlayerMarker;
+use crate::input::PlayerInput;
+use crate::player_movement::{Distance, Jump, Run, SideChecks, PLAYER_GROUP};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// Walking into a crate pushes it at a speed set by its mass. Holding Grab next to one fixes it to the
+// player with a joint, so it can be pulled along too.
+
+/// Pushing and pulling crates, on the player.
+#[derive(Component, Debug, Clone)]
+pub struct PushGrab {
+    pub push_speed: Distance, // speed pushing something weightless, per second
+    pub mass_penalty: f32,    // push speed is divided by 1 + mass * this
+    pub pushing: Option<Entity>, // crate being walked into
+    pub grabbing: Option<(Entity, f32)>, // crate held, and the side it's on
+}
+impl Default for PushGrab {
+    fn default() -> Self {
+        Self {
+            push_speed: Distance::meters(5.0),
+            mass_penalty: 0.5,
+            pushing: None,
+            grabbing: None,
+        }
+    }
+}
+impl PushGrab {
+    pub fn speed_with(&self, mass: f32) -> f32 {
+        self.push_speed.to_pixels() / (1.0 + mass.max(0.0) * self.mass_penalty)
+    }
+}
+
+/// How far a held crate can get from where it was grabbed before it slips out of the player's hands.
+const GRAB_SLIP_DISTANCE: f32 = 8.0;
+
+/// Runs after the movement updates, so it can slow the player down to the crate's pace.
+#[allow(clippy::type_complexity)]
+pub fn update_push_grab(
+    player_input: Res<PlayerInput>,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut PushGrab,
+            &mut Run,
+            &Jump,
+            &SideChecks,
+            Has<Dying>,
+        ),
+        With<PlayerMarker>,
+    >,
+    mut crates: Query<
+        (
+            &Transform,
+            &ReadMassProperties,
+            &mut CollisionGroups,
+            Option<&ImpulseJoint>,
+        ),
+        With<CrateMarker>,
+    >,
+    mut commands: Commands,
+) {
+    let Ok((player, transform, mut push_grab, mut run, jump, sides, dying)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    let holding = player_input.grab && jump.grounded && !dying;
+
+    if let Some((held, _)) = push_grab.grabbing {
+        let slipped = match crates.get(held) {
+            Ok((crate_transform, _, _, Some(joint))) => {
+                let anchor = position + joint.data.as_ref().local_anchor1();
+                crate_transform.translation.truncate().distance(anchor) > GRAB_SLIP_DISTANCE
+            }
+            _ => true,
+        };
+        if !holding || slipped {
+            if let Ok((_, _, mut groups, _)) = crates.get_mut(held) {
+                *groups = CollisionGroups::default();
+                commands.entity(held).remove::<ImpulseJoint>();
+            }
+            push_grab.grabbing = None;
+        }
+    } else if holding {
+        let side = [-1.0, 1.0]
+            .into_iter()
+            .find(|side| sides.crate_on(*side).is_some());
+        if let Some(side) = side {
+            let held = sides.crate_on(side).unwrap();
+            if let Ok((crate_transform, _, mut groups, _)) = crates.get_mut(held) {
+                let offset = crate_transform.translation.truncate() - position;
+                commands.entity(held).insert(ImpulseJoint::new(
+                    player,
+                    FixedJointBuilder::new().local_anchor1(offset),
+                ));
+                // The joint carries it now, keep the player from bumping into it.
+                *groups = CollisionGroups::new(Group::ALL, !PLAYER_GROUP);
+                push_grab.grabbing = Some((held, side));
+            }
+        }
+    }
+
+    push_grab.pushing = match push_grab.grabbing {
+        Some(_) => None,
+        None => run
+            .running
+            .and_then(|direction| sides.crate_on(direction))
+            .filter(|_| jump.grounded),
+    };
+    let heavy = push_grab
+        .grabbing
+        .map(|(held, _)| held)
+        .or(push_grab.pushing);
+    if let Some((_, mass, _, _)) = heavy.and_then(|heavy| crates.get(heavy).ok()) {
+        let speed = push_grab.speed_with(mass.get().mass);
+        run.velocity = run.velocity.clamp(-speed, speed);
+        run.current_run_time = 0.0;
+    }
+}
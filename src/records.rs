@@ -0,0 +1,132 @@
+use crate::events_systems::RaceFinished;
+use crate::ghost::{Ghost, GhostRecorder};
+use crate::level_loader::level_iid_from_selection;
+use crate::replay::InputPlayback;
+use crate::storage;
+use crate::{PlayerText, Split};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub struct RecordsPlugin;
+impl Plugin for RecordsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Records::load());
+        app.observe(record_finished_race);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RunRecord {
+    pub time: f32,
+    #[serde(default)]
+    pub splits: Vec<Split>, // checkpoint times, compared against on later runs
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LevelRecord {
+    pub best: Option<RunRecord>,
+    pub recent: Vec<RunRecord>, // newest last, capped at Records::RECENT_RUNS
+    #[serde(default)]
+    pub ghost: Option<Ghost>, // recording of the best run
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RunResult {
+    FirstRun,
+    NewBest { previous: f32 },
+    OffBest { best: f32 },
+}
+impl RunResult {
+    pub fn describe(&self, time: f32) -> String {
+        match self {
+            RunResult::FirstRun => "First run, new best!".to_string(),
+            RunResult::NewBest { previous } => format!("New best! -{:.3}s", previous - time),
+            RunResult::OffBest { best } => format!("+{:.3}s off best", time - best),
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+pub struct Records {
+    levels: HashMap<String, LevelRecord>, // keyed by level iid
+}
+impl Records {
+    pub const RECENT_RUNS: usize = 10;
+    const PATH: &'static str = "records.ron";
+
+    pub fn get(&self, level_iid: &str) -> Option<&LevelRecord> {
+        self.levels.get(level_iid)
+    }
+    pub fn best(&self, level_iid: &str) -> Option<f32> {
+        self.get(level_iid)
+            .and_then(|record| record.best.as_ref())
+            .map(|best| best.time)
+    }
+    pub fn submit(&mut self, level_iid: &str, run: RunRecord, ghost: Option<Ghost>) -> RunResult {
+        let record = self.levels.entry(level_iid.to_string()).or_default();
+        let result = match &record.best {
+            None => RunResult::FirstRun,
+            Some(best) if run.time < best.time => RunResult::NewBest {
+                previous: best.time,
+            },
+            Some(best) => RunResult::OffBest { best: best.time },
+        };
+        if !matches!(result, RunResult::OffBest { .. }) {
+            record.best = Some(run.clone());
+            record.ghost = ghost;
+        }
+        record.recent.push(run);
+        if record.recent.len() > Self::RECENT_RUNS {
+            record.recent.remove(0);
+        }
+        result
+    }
+    pub fn load() -> Self {
+        storage::load_ron(Self::PATH).unwrap_or_default()
+    }
+    pub fn save(&self) {
+        storage::save_ron(Self::PATH, self);
+    }
+}
+
+pub fn record_finished_race(
+    trigger: Trigger<RaceFinished>,
+    mut records: ResMut<Records>,
+    mut text_query: Query<&mut Text, With<PlayerText>>,
+    recorder: Query<&GhostRecorder>,
+    playback: Res<InputPlayback>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if playback.is_playing() {
+        return;
+    }
+    let finished = trigger.event();
+    let Some(level_iid) =
+        level_iid_from_selection(&finished.level, &ldtk_projects, &ldtk_project_assets)
+    else {
+        warn!("Finished a race on a level that isn't in the project.");
+        return;
+    };
+    let result = records.submit(
+        &level_iid,
+        RunRecord {
+            time: finished.time,
+            splits: finished.splits.clone(),
+        },
+        recorder
+            .get_single()
+            .ok()
+            .map(|recorder| recorder.0.clone()),
+    );
+    records.save();
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = format!(
+            "You've finished! {:.3}\n{}",
+            finished.time,
+            result.describe(finished.time)
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use crate::entities::player::{PlayerMarker, RespawnPoint};
+use crate::events_systems::RaceFinished;
+use crate::input::{Action, ActionState, PlayerInput};
+use crate::level_loader::level_iid_from_selection;
+use crate::menu::PauseState;
+use crate::player_controls::apply_player_input;
+use crate::player_movement::{Jump, Run, WallJump};
+use crate::storage;
+use crate::{GameState, PlayerText, RaceTime, RestoredRace};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::{LevelIid, LevelSelection, Respawn};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputRecorder>();
+        app.init_resource::<InputPlayback>();
+        app.add_systems(
+            FixedUpdate,
+            (play_back_input, start_input_recording, record_input)
+                .chain()
+                .before(apply_player_input)
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.add_systems(
+            Update,
+            (
+                stop_replays,
+                start_playback.run_if(not(in_state(PauseState::Paused))),
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.observe(finish_input_recording);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PhysicsSettings {
+    pub gravity: [f32; 2],
+    pub timestep: f32,
+    pub substeps: usize,
+}
+impl PhysicsSettings {
+    pub fn current(config: &RapierConfiguration) -> Self {
+        let (timestep, substeps) = match config.timestep_mode {
+            TimestepMode::Fixed { dt, substeps } => (dt, substeps),
+            TimestepMode::Variable {
+                max_dt, substeps, ..
+            } => (max_dt, substeps),
+            TimestepMode::Interpolated { dt, substeps, .. } => (dt, substeps),
+        };
+        Self {
+            gravity: config.gravity.to_array(),
+            timestep,
+            substeps,
+        }
+    }
+}
+
+/// Player state at the moment the race started, restored before playing the inputs back.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayStart {
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub respawn: [f32; 2],
+    pub jump: Jump,
+    pub run: Run,
+    pub wall_jump: WallJump,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputRecording {
+    pub version: u32,
+    pub level_iid: String,
+    pub physics: PhysicsSettings,
+    pub start: ReplayStart,
+    pub time: Option<f32>, // finish time, shown when playback doesn't match
+    pub inputs: Vec<(u8, u32)>, // run length encoded PlayerInput bits, one per fixed tick
+}
+impl InputRecording {
+    pub const VERSION: u32 = 7;
+    pub const PATH: &'static str = "replay.ron";
+
+    pub fn push(&mut self, input: PlayerInput) {
+        let bits = input.to_bits();
+        match self.inputs.last_mut() {
+            Some((last, count)) if *last == bits => *count += 1,
+            _ => self.inputs.push((bits, 1)),
+        }
+    }
+    /// Fixed ticks recorded, up to and including the one the race finished on.
+    pub fn ticks(&self) -> usize {
+        self.inputs.iter().map(|(_, count)| *count as usize).sum()
+    }
+    pub fn expand(&self) -> Vec<PlayerInput> {
+        self.inputs
+            .iter()
+            .flat_map(|(bits, count)| {
+                std::iter::repeat_n(PlayerInput::from_bits(*bits), *count as usize)
+            })
+            .collect()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InputRecorder(Option<InputRecording>);
+
+#[derive(Resource, Default)]
+pub struct InputPlayback {
+    recording: Option<InputRecording>,
+    inputs: Vec<PlayerInput>,
+    tick: usize,
+}
+impl InputPlayback {
+    pub fn is_playing(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+pub fn record_input(
+    player_input: Res<PlayerInput>,
+    mut recorder: ResMut<InputRecorder>,
+    race_time: Query<(), With<RaceTime>>,
+) {
+    if race_time.is_empty() {
+        return;
+    }
+    if let Some(recording) = recorder.0.as_mut() {
+        recording.push(*player_input);
+    }
+}
+
+pub fn play_back_input(mut player_input: ResMut<PlayerInput>, mut playback: ResMut<InputPlayback>) {
+    if !playback.is_playing() {
+        return;
+    }
+    *player_input = playback
+        .inputs
+        .get(playback.tick)
+        .copied()
+        .unwrap_or_default();
+    playback.tick += 1;
+}
+
+/// Runs on the fixed tick the race starts, before that tick's input is recorded.
+#[allow(clippy::type_complexity)]
+pub fn start_input_recording(
+    player: Query<
+        (
+            &RaceTime,
+            &Transform,
+            &RespawnPoint,
+            &Jump,
+            &Run,
+            &WallJump,
+            Option<Ref<RestoredRace>>,
+        ),
+        Added<RaceTime>,
+    >,
+    playback: Res<InputPlayback>,
+    mut recorder: ResMut<InputRecorder>,
+    rapier_config: Res<RapierConfiguration>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if playback.is_playing() {
+        return;
+    }
+    for (race_time, transform, respawn_point, jump, run, wall_jump, restored) in player.iter() {
+        if restored.is_some_and(|restored| restored.is_added()) {
+            continue;
+        }
+        let Some(level_iid) =
+            level_iid_from_selection(&race_time.level, &ldtk_projects, &ldtk_project_assets)
+        else {
+            continue;
+        };
+        recorder.0 = Some(InputRecording {
+            version: InputRecording::VERSION,
+            level_iid,
+            physics: PhysicsSettings::current(&rapier_config),
+            start: ReplayStart {
+                position: transform.translation.truncate().to_array(),
+                respawn: respawn_point.0.to_array(),
+                jump: jump.clone(),
+                run: run.clone(),
+                wall_jump: wall_jump.clone(),
+            },
+            time: None,
+            inputs: vec![],
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_playback(
+    action_state: Res<ActionState>,
+    mut playback: ResMut<InputPlayback>,
+    mut player: Query<
+        (Entity, &mut Transform, &mut Jump, &mut Run, &mut WallJump),
+        With<PlayerMarker>,
+    >,
+    mut text_query: Query<&mut Text, With<PlayerText>>,
+    mut level_selection: ResMut<LevelSelection>,
+    levels: Query<(Entity, &LevelIid)>,
+    rapier_config: Res<RapierConfiguration>,
+    mut commands: Commands,
+) {
+    if !action_state.just_pressed(Action::Replay) {
+        return;
+    }
+    let mut msg = |msg: &str| {
+        if let Ok(mut text) = text_query.get_single_mut() {
+            text.sections[0].value = msg.into();
+        }
+    };
+    let Some(recording) = storage::load_ron::<InputRecording>(InputRecording::PATH) else {
+        msg("No replay to play.");
+        return;
+    };
+    if recording.version != InputRecording::VERSION {
+        msg("Replay is from a different version.");
+        return;
+    }
+    if recording.physics != PhysicsSettings::current(&rapier_config) {
+        msg("Replay was recorded with different physics.");
+        return;
+    }
+    let Ok((player, mut transform, mut jump, mut run, mut wall_jump)) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    transform.translation = Vec2::from(recording.start.position).extend(transform.translation.z);
+    *jump = recording.start.jump.clone();
+    *run = recording.start.run.clone();
+    *wall_jump = recording.start.wall_jump.clone();
+
+    let level_iid = LevelIid::new(recording.level_iid.clone());
+    for (level_entity, iid) in levels.iter() {
+        if *iid == level_iid {
+            commands.entity(level_entity).insert(Respawn);
+        }
+    }
+    *level_selection = LevelSelection::Iid(level_iid);
+    commands.entity(player).insert((
+        RaceTime::new(level_selection.clone()),
+        RespawnPoint(Vec2::from(recording.start.respawn)),
+    ));
+    msg("Replaying...");
+
+    playback.inputs = recording.expand();
+    playback.tick = 0;
+    playback.recording = Some(recording);
+}
+
+pub fn stop_replays(
+    race_time: Query<(), With<RaceTime>>,
+    mut recorder: ResMut<InputRecorder>,
+    mut playback: ResMut<InputPlayback>,
+) {
+    if !race_time.is_empty() {
+        return;
+    }
+    recorder.0 = None;
+    if playback.is_playing() {
+        *playback = InputPlayback::default();
+    }
+}
+
+pub fn finish_input_recording(
+    trigger: Trigger<RaceFinished>,
+    mut recorder: ResMut<InputRecorder>,
+    playback: Res<InputPlayback>,
+    mut text_query: Query<&mut Text, With<PlayerText>>,
+) {
+    let finished = trigger.event();
+    if let Some(recording) = playback.recording.as_ref() {
+        // Times are summed up frame by frame, compare ticks so float error can't fail a good replay.
+        let verified = recording.time.is_some() && playback.tick == recording.ticks();
+        if let Ok(mut text) = text_query.get_single_mut() {
+            text.sections[0].value = match (verified, recording.time) {
+                (true, _) => format!("Replay verified! {:.3}", finished.time),
+                (false, Some(time)) => format!(
+                    "Replay desynced, {:.3} instead of {:.3}",
+                    finished.time, time
+                ),
+                (false, None) => format!("Replay finished. {:.3}", finished.time),
+            };
+        }
+    } else if let Some(mut recording) = recorder.0.take() {
+        recording.time = Some(finished.time);
+        storage::save_ron(InputRecording::PATH, &recording);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::animation::{MousePosition, Spline, SplineKind};
+use crate::assets::Levels;
+use crate::entities::platform::{MovingPlatform, PathMode, SplinePaths};
+use crate::input::{action_toggle_active, Action};
+use crate::{storage, GameState};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::EntityIid;
+use bevy_inspector_egui::bevy_egui::egui;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPlugin};
+
+// Path editing, toggled with 5. Drag points with the mouse, right click removes one, the panel adds them
+// and picks the curve. Save writes every path to assets/platform.paths.ron, which platforms and camera
+// rails load over the points placed in LDtk.
+
+pub struct SplineEditorPlugin;
+impl Plugin for SplineEditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<SplineEditor>();
+        app.add_systems(
+            Update,
+            (spline_editor_panel, drag_spline_points, draw_splines)
+                .chain()
+                .run_if(in_state(GameState::InGame))
+                .run_if(action_toggle_active(false, Action::ToggleSplineEditor)),
+        );
+    }
+}
+
+/// Paths are snapped to the same grid as the level.
+const GRID_SIZE: f32 = 16.0;
+/// How close the mouse has to be to pick up a point.
+const PICK_RADIUS: f32 = 4.0;
+
+#[derive(Resource)]
+pub struct SplineEditor {
+    pub selected: Option<(Entity, usize)>, // path and point being edited
+    pub snap: bool,
+    dragging: bool,
+}
+impl Default for SplineEditor {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            snap: true,
+            dragging: false,
+        }
+    }
+}
+impl SplineEditor {
+    fn snapped(&self, offset: Vec2) -> Vec2 {
+        match self.snap {
+            true => (offset / GRID_SIZE).round() * GRID_SIZE,
+            false => offset,
+        }
+    }
+}
+
+/// Where a path's points are measured from. Platforms move, so theirs stay where the platform started.
+fn path_origin(transform: &GlobalTransform, platform: Option<&MovingPlatform>) -> Vec2 {
+    platform
+        .and_then(MovingPlatform::origin)
+        .unwrap_or(transform.translation().truncate())
+}
+fn path_closed(platform: Option<&MovingPlatform>) -> bool {
+    platform.is_some_and(|platform| platform.mode == PathMode::Loop)
+}
+
+pub fn spline_editor_panel(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<SplineEditor>,
+    mut paths: Query<(
+        Entity,
+        &mut Spline,
+        Option<&MovingPlatform>,
+        Option<&EntityIid>,
+    )>,
+    levels: Res<Levels>,
+    saved: Res<Assets<SplinePaths>>,
+    mut status: Local<String>,
+) {
+    egui::Window::new("Paths").show(contexts.ctx_mut(), |ui| {
+        ui.checkbox(&mut editor.snap, format!("Snap to {} px", GRID_SIZE));
+        let selected = editor
+            .selected
+            .and_then(|(entity, point)| Some((paths.get_mut(entity).ok()?, point)));
+        match selected {
+            None => {
+                ui.label("Click a point on a path to edit it.");
+            }
+            Some(((_, mut path, platform, _), point)) => {
+                ui.horizontal(|ui| {
+                    for kind in SplineKind::ALL {
+                        if ui
+                            .selectable_label(path.kind == kind, format!("{:?}", kind))
+                            .clicked()
+                        {
+                            path.kind = kind;
+                        }
+                    }
+                });
+                ui.label(format!(
+                    "{} points, {:.0} px long",
+                    path.points.len(),
+                    path.arc_length(path_closed(platform))
+                ));
+                if let Some(position) = path.points.get(point) {
+                    ui.label(format!(
+                        "Point {}: {:.0}, {:.0}",
+                        point, position.x, position.y
+                    ));
+                }
+                ui.horizontal(|ui| {
+                    // New points go halfway to the next one, or carry on past the end.
+                    if ui.button("Insert after").clicked() && point < path.points.len() {
+                        let here = path.points[point];
+                        let next = match path.points.get(point + 1) {
+                            Some(next) => (here + *next) / 2.0,
+                            None => here + Vec2::new(GRID_SIZE, 0.0),
+                        };
+                        path.points.insert(point + 1, editor.snapped(next));
+                        editor.selected = editor.selected.map(|(entity, _)| (entity, point + 1));
+                    }
+                    // The first point is where the platform starts, keep it.
+                    if ui.button("Remove").clicked() && point > 0 && point < path.points.len() {
+                        path.points.remove(point);
+                        editor.selected = editor.selected.map(|(entity, _)| (entity, point - 1));
+                    }
+                });
+            }
+        }
+        if ui.button("Save").clicked() {
+            let mut all = saved.get(&levels.paths).cloned().unwrap_or_default();
+            for (_, path, _, iid) in paths.iter() {
+                if let Some(iid) = iid {
+                    all.0.insert(iid.as_str().to_string(), path.clone());
+                }
+            }
+            storage::save_ron(SplinePaths::PATH, &all);
+            *status = format!("Saved {} paths to {}", all.0.len(), SplinePaths::PATH);
+        }
+        if !status.is_empty() {
+            ui.label(status.as_str());
+        }
+    });
+}
+
+pub fn drag_spline_points(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<SplineEditor>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mouse: Res<MousePosition>,
+    mut paths: Query<(
+        Entity,
+        &mut Spline,
+        &GlobalTransform,
+        Option<&MovingPlatform>,
+    )>,
+) {
+    let mouse = mouse.0;
+    if mouse_button.just_released(MouseButton::Left) {
+        editor.dragging = false;
+    }
+    if contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let nearest = paths
+        .iter()
+        .flat_map(|(entity, path, transform, platform)| {
+            let origin = path_origin(transform, platform);
+            path.points
+                .iter()
+                .enumerate()
+                .map(move |(index, point)| (entity, index, (origin + *point).distance(mouse)))
+        })
+        .filter(|(_, _, distance)| *distance < PICK_RADIUS)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(entity, index, _)| (entity, index));
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        if let Some(nearest) = nearest {
+            editor.selected = Some(nearest);
+            editor.dragging = true;
+        }
+    }
+    if mouse_button.just_pressed(MouseButton::Right) {
+        if let Some((entity, index)) = nearest.filter(|(_, index)| *index > 0) {
+            if let Ok((_, mut path, _, _)) = paths.get_mut(entity) {
+                path.points.remove(index);
+                editor.selected = None;
+            }
+        }
+    }
+    let Some((entity, index)) = editor.selected.filter(|_| editor.dragging) else {
+        return;
+    };
+    if let Ok((_, mut path, transform, platform)) = paths.get_mut(entity) {
+        let offset = editor.snapped(mouse - path_origin(transform, platform));
+        // The first point is where the platform starts, keep it.
+        if index > 0 && path.points.get(index).is_some_and(|point| *point != offset) {
+            path.points[index] = offset;
+        }
+    }
+}
+
+/// Curves in blue with green tangents, points in red, the selected one in yellow.
+pub fn draw_splines(
+    editor: Res<SplineEditor>,
+    paths: Query<(Entity, &Spline, &GlobalTransform, Option<&MovingPlatform>)>,
+    mut gizmos: Gizmos,
+) {
+    for (entity, path, transform, platform) in paths.iter() {
+        let origin = path_origin(transform, platform);
+        let curve = path.curve(path_closed(platform));
+        if !curve.segments.is_empty() {
+            let subdivisions = curve.segments.len() * 16;
+            gizmos.linestrip_2d(
+                curve.iter_positions(subdivisions).map(|p| origin + p),
+                Color::srgb(0.2, 0.4, 1.0),
+            );
+            for (position, velocity) in curve
+                .iter_positions(subdivisions)
+                .zip(curve.iter_velocities(subdivisions))
+                .step_by(4)
+            {
+                let tangent = velocity.normalize_or_zero() * 4.0;
+                gizmos.line_2d(
+                    origin + position - tangent,
+                    origin + position + tangent,
+                    Color::srgb(0.0, 0.9, 0.3),
+                );
+            }
+        }
+        for (index, point) in path.points.iter().enumerate() {
+            let color = match editor.selected == Some((entity, index)) {
+                true => Color::srgb(1.0, 0.9, 0.0),
+                false => Color::srgb(1.0, 0.2, 0.2),
+            };
+            gizmos.circle_2d(origin + *point, 2.0, color);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::events_systems::RaceFinished;
+use crate::level_loader::level_iid_from_selection;
+use crate::records::Records;
+use crate::{GameState, RaceTime, RestoredRace, Split};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+
+pub struct SplitsPlugin;
+impl Plugin for SplitsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SplitComparison>();
+        app.add_systems(OnEnter(GameState::InGame), spawn_splits_hud);
+        app.add_systems(
+            Update,
+            (
+                start_split_comparison,
+                track_splits,
+                update_splits_hud.run_if(resource_changed::<SplitComparison>),
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+        app.observe(finish_split_comparison);
+    }
+}
+
+const AHEAD_COLOR: Color = Color::srgb(0.2, 0.9, 0.2);
+const BEHIND_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+/// The current race's splits next to the best run's, kept around after the finish so you can read them.
+#[derive(Resource, Default)]
+pub struct SplitComparison {
+    best: Vec<Split>,       // splits of the best run, taken when this race started
+    best_time: Option<f32>, // finish time of that run
+    splits: Vec<Split>,
+    time: Option<f32>, // set once finished
+}
+impl SplitComparison {
+    /// Time gained (negative) or lost (positive) against the best run at the same checkpoint.
+    pub fn delta(&self, split: &Split) -> Option<f32> {
+        self.best
+            .iter()
+            .find(|best| best.checkpoint == split.checkpoint)
+            .map(|best| split.time - best.time)
+    }
+}
+
+#[derive(Component)]
+pub struct SplitsHud;
+
+pub fn spawn_splits_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::default(),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                right: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        },
+        SplitsHud,
+        StateScoped(GameState::InGame),
+    ));
+}
+
+pub fn start_split_comparison(
+    race_time: Query<(&RaceTime, Option<Ref<RestoredRace>>), Added<RaceTime>>,
+    records: Res<Records>,
+    mut comparison: ResMut<SplitComparison>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok((race_time, restored)) = race_time.get_single() else {
+        return;
+    };
+    if restored.is_some_and(|restored| restored.is_added()) {
+        return;
+    }
+    let best = level_iid_from_selection(&race_time.level, &ldtk_projects, &ldtk_project_assets)
+        .and_then(|level_iid| records.get(&level_iid))
+        .and_then(|record| record.best.clone());
+    *comparison = SplitComparison {
+        best_time: best.as_ref().map(|best| best.time),
+        best: best.map(|best| best.splits).unwrap_or_default(),
+        ..default()
+    };
+}
+
+pub fn track_splits(race_time: Query<&RaceTime>, mut comparison: ResMut<SplitComparison>) {
+    let Ok(race_time) = race_time.get_single() else {
+        return;
+    };
+    if race_time.splits.len() != comparison.splits.len() {
+        comparison.splits = race_time.splits.clone();
+    }
+}
+
+pub fn finish_split_comparison(
+    trigger: Trigger<RaceFinished>,
+    mut comparison: ResMut<SplitComparison>,
+) {
+    let finished = trigger.event();
+    comparison.splits = finished.splits.clone();
+    comparison.time = Some(finished.time);
+}
+
+pub fn update_splits_hud(
+    comparison: Res<SplitComparison>,
+    mut hud: Query<&mut Text, With<SplitsHud>>,
+) {
+    let Ok(mut text) = hud.get_single_mut() else {
+        return;
+    };
+    let line = |label: String, time: f32, delta: Option<f32>| {
+        let mut sections = vec![TextSection::new(
+            format!("{label:<8}{time:>8.3}"),
+            TextStyle::default(),
+        )];
+        if let Some(delta) = delta {
+            sections.push(TextSection::new(
+                format!(" {delta:+.3}"),
+                TextStyle {
+                    color: if delta <= 0.0 {
+                        AHEAD_COLOR
+                    } else {
+                        BEHIND_COLOR
+                    },
+                    ..default()
+                },
+            ));
+        }
+        sections.push(TextSection::new("\n", TextStyle::default()));
+        sections
+    };
+    text.sections = comparison
+        .splits
+        .iter()
+        .enumerate()
+        .flat_map(|(i, split)| {
+            line(
+                format!("Split {}", i + 1),
+                split.time,
+                comparison.delta(split),
+            )
+        })
+        .collect();
+    if let Some(time) = comparison.time {
+        text.sections.extend(line(
+            "Finish".to_string(),
+            time,
+            comparison.best_time.map(|best| time - best),
+        ));
+    }
+}
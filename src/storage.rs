@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+// Small save/load helpers, files next to the executable on desktop and local storage on web.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Option<String> {
+    std::fs::read_to_string(name).ok()
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, data: &str) {
+    if let Err(e) = std::fs::write(name, data) {
+        error!("Failed to write {}. {:?}", name, e);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+const KEY_PREFIX: &str = "run_for_fun/";
+/// Keys from before everything went under KEY_PREFIX, read until the file is saved under the new one.
+#[cfg(target_arch = "wasm32")]
+const LEGACY_KEYS: &[(&str, &str)] = &[("records.ron", "run_for_fun_records")];
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Option<String> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    let legacy = || {
+        let (_, key) = LEGACY_KEYS.iter().find(|(file, _)| *file == name)?;
+        storage.get_item(key).ok()?
+    };
+    storage
+        .get_item(&format!("{KEY_PREFIX}{name}"))
+        .ok()?
+        .or_else(legacy)
+}
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, data: &str) {
+    if let Some(Ok(Some(storage))) = web_sys::window().map(|w| w.local_storage()) {
+        if let Err(e) = storage.set_item(&format!("{KEY_PREFIX}{name}"), data) {
+            error!("Failed to write {} to local storage. {:?}", name, e);
+        }
+    }
+}
+
+pub fn load_ron<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    match ron::from_str::<T>(&read(name)?) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            error!("Failed to parse {}. {:?}", name, e);
+            None
+        }
+    }
+}
+pub fn save_ron<T: serde::Serialize>(name: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(data) => write(name, &data),
+        Err(e) => error!("Failed to serialize {}. {:?}", name, e),
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::{HashMap, HashSet};
+
+// Turns a grid of collider tiles into as few rectangles and slope hulls as possible, kept free of Bevy
+// so it can be tested.
+
+/// Which edge of the tile a partial collider hugs.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Align {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// Solid part of one tile, `fill` is the fraction of the tile's height.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cell {
+    pub fill: f32,
+    pub align: Align,
+}
+impl Cell {
+    pub const FULL: Cell = Cell {
+        fill: 1.0,
+        align: Align::Bottom,
+    };
+    pub fn new(fill: f32, align: Align) -> Self {
+        Self { fill, align }
+    }
+    /// Reads the tile custom data tags, untagged partial colliders hang from the top like they always have.
+    pub fn from_tile_data(data: &str) -> Option<Cell> {
+        let cell = match data {
+            "collider" => Cell::FULL,
+            "half_collider" | "half_collider_top" => Cell::new(0.5, Align::Top),
+            "half_collider_bottom" => Cell::new(0.5, Align::Bottom),
+            "quarter_collider" | "quarter_collider_top" => Cell::new(0.25, Align::Top),
+            "quarter_collider_bottom" => Cell::new(0.25, Align::Bottom),
+            _ => return None,
+        };
+        Some(cell)
+    }
+    pub fn is_full(&self) -> bool {
+        self.fill >= 1.0
+    }
+}
+
+/// A merged block of cells, in tile coordinates with y up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub cell: Cell,
+}
+impl TileRect {
+    /// Min and max corners in pixels, for tiles centered on their grid point.
+    pub fn bounds(&self, tile_size: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        let [tile_w, tile_h] = tile_size;
+        let left = (self.x as f32 - 0.5) * tile_w;
+        let right = left + self.width as f32 * tile_w;
+        let bottom = (self.y as f32 - 0.5) * tile_h;
+        let top = bottom + self.height as f32 * tile_h;
+        let solid = self.cell.fill * tile_h;
+        match (self.cell.is_full(), self.cell.align) {
+            (true, _) => ([left, bottom], [right, top]),
+            (false, Align::Bottom) => ([left, bottom], [right, bottom + solid]),
+            (false, Align::Top) => ([left, top - solid], [right, top]),
+        }
+    }
+}
+
+/// Greedy rectangle merge. Cells only merge with identical cells, so runs split wherever the height or
+/// alignment changes, and partial cells only merge along rows since a stack of half tiles isn't a solid
+/// block. Row by row from the bottom left, each new rectangle takes the longest run it can, then grows
+/// upward while the whole run matches.
+/// Neighbouring rectangles still meet at internal edges, so there are fewer seams to catch on, not none.
+pub fn merge_cells(cells: &HashMap<(i32, i32), Cell>) -> Vec<TileRect> {
+    let Some(min_x) = cells.keys().map(|(x, _)| *x).min() else {
+        return vec![];
+    };
+    let min_y = cells.keys().map(|(_, y)| *y).min().unwrap_or_default();
+    let max_x = cells.keys().map(|(x, _)| *x).max().unwrap_or_default();
+    let max_y = cells.keys().map(|(_, y)| *y).max().unwrap_or_default();
+
+    let mut used: HashSet<(i32, i32)> = HashSet::new();
+    let mut rects = vec![];
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let Some(&cell) = cells.get(&(x, y)) else {
+                continue;
+            };
+            if used.contains(&(x, y)) {
+                continue;
+            }
+            let free = |x: i32, y: i32, used: &HashSet<(i32, i32)>| {
+                cells.get(&(x, y)) == Some(&cell) && !used.contains(&(x, y))
+            };
+            let mut width = 1;
+            while free(x + width, y, &used) {
+                width += 1;
+            }
+            let mut height = 1;
+            if cell.is_full() {
+                while (x..x + width).all(|cx| free(cx, y + height, &used)) {
+                    height += 1;
+                }
+            }
+            for cy in y..y + height {
+                for cx in x..x + width {
+                    used.insert((cx, cy));
+                }
+            }
+            rects.push(TileRect {
+                x,
+                y,
+                width,
+                height,
+                cell,
+            });
+        }
+    }
+    rects
+}
+
+/// A slope tile, solid from the bottom of the tile up to a straight surface. `left` and `right` are the
+/// surface heights at the tile's edges, as a fraction of the tile.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Slope {
+    pub left: f32,
+    pub right: f32,
+}
+impl Slope {
+    /// Reads the tile custom data tags. The 26 slopes (2:1, about 26.6°) take two tiles, a low and a high half,
+    /// and the older `slope_22_` tags read as the same halves.
+    pub fn from_tile_data(data: &str) -> Option<Slope> {
+        let (left, right) = match data {
+            "slope_45_up" => (0.0, 1.0),
+            "slope_45_down" => (1.0, 0.0),
+            "slope_26_up_low" | "slope_22_up_low" => (0.0, 0.5),
+            "slope_26_up_high" | "slope_22_up_high" => (0.5, 1.0),
+            "slope_26_down_high" | "slope_22_down_high" => (1.0, 0.5),
+            "slope_26_down_low" | "slope_22_down_low" => (0.5, 0.0),
+            _ => return None,
+        };
+        Some(Slope { left, right })
+    }
+    fn rise(&self) -> f32 {
+        self.right - self.left
+    }
+    /// Corners of tile `(x, y)`'s solid part, with the tile spanning x..x + 1 and y..y + 1.
+    pub fn polygon(&self, (x, y): (i32, i32)) -> Vec<[f32; 2]> {
+        let (x, y) = (x as f32, y as f32);
+        let mut points = vec![[x, y], [x + 1.0, y]];
+        if self.right > 0.0 {
+            points.push([x + 1.0, y + self.right]);
+        }
+        if self.left > 0.0 {
+            points.push([x, y + self.left]);
+        }
+        points
+    }
+    /// Where a slope carrying on from this one to the right would be, if `next` lines up with it.
+    fn next_tile(&self, (x, y): (i32, i32), next: &Slope) -> Option<(i32, i32)> {
+        if next.rise() != self.rise() {
+            return None;
+        }
+        if next.left == self.right {
+            Some((x + 1, y))
+        } else if self.right == 1.0 && next.left == 0.0 {
+            Some((x + 1, y + 1))
+        } else if self.right == 0.0 && next.left == 1.0 {
+            Some((x + 1, y - 1))
+        } else {
+            None
+        }
+    }
+}
+
+/// Joins slope tiles whose surfaces carry on from one to the next into single convex polygons, given as
+/// points to take the hull of, in the same coordinates as `Slope::polygon`.
+/// The hull of a run also fills the corner under each step, so tiles above the run's lowest row only join
+/// when the cell below them is a full collider, otherwise the run is split there.
+pub fn merge_slopes(
+    slopes: &HashMap<(i32, i32), Slope>,
+    cells: &HashMap<(i32, i32), Cell>,
+) -> Vec<Vec<[f32; 2]>> {
+    let next = |tile: (i32, i32)| {
+        let slope = slopes[&tile];
+        [(0, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .map(|(dx, dy)| (tile.0 + 1 + dx, tile.1 + dy))
+            .find(|candidate| {
+                slopes
+                    .get(candidate)
+                    .and_then(|next| slope.next_tile(tile, next))
+                    == Some(*candidate)
+            })
+    };
+    let starts = slopes.keys().filter(|tile| {
+        !slopes
+            .keys()
+            .any(|other| other != *tile && next(*other) == Some(**tile))
+    });
+    let mut starts = starts.copied().collect::<Vec<_>>();
+    starts.sort();
+
+    let supported = |(x, y): (i32, i32)| cells.get(&(x, y - 1)).is_some_and(Cell::is_full);
+    let mut polygons = vec![];
+    for start in starts {
+        let mut run = vec![start];
+        while let Some(tile) = next(*run.last().unwrap()) {
+            run.push(tile);
+        }
+        let mut piece: Vec<(i32, i32)> = vec![];
+        for tile in run {
+            let mut joined = piece.clone();
+            joined.push(tile);
+            let lowest = joined.iter().map(|(_, y)| *y).min().unwrap_or_default();
+            if !joined.iter().all(|t| t.1 == lowest || supported(*t)) {
+                polygons.push(piece_points(slopes, &piece));
+                joined = vec![tile];
+            }
+            piece = joined;
+        }
+        polygons.push(piece_points(slopes, &piece));
+    }
+    polygons
+}
+fn piece_points(slopes: &HashMap<(i32, i32), Slope>, piece: &[(i32, i32)]) -> Vec<[f32; 2]> {
+    piece
+        .iter()
+        .flat_map(|tile| slopes[tile].polygon(*tile))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rows top to bottom like you'd draw them, '#' full, '=' half, '^' quarter, both on top like the
+    // untagged tiles, '_' quarter on the bottom.
+    fn grid(rows: &[&str]) -> HashMap<(i32, i32), Cell> {
+        let mut cells = HashMap::new();
+        for (row, line) in rows.iter().rev().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let cell = match c {
+                    '#' => Cell::FULL,
+                    '=' => Cell::new(0.5, Align::Top),
+                    '^' => Cell::new(0.25, Align::Top),
+                    '_' => Cell::new(0.25, Align::Bottom),
+                    _ => continue,
+                };
+                cells.insert((x as i32, row as i32), cell);
+            }
+        }
+        cells
+    }
+
+    fn covered(rects: &[TileRect]) -> HashMap<(i32, i32), Cell> {
+        let mut cells = HashMap::new();
+        for rect in rects {
+            for y in rect.y..rect.y + rect.height {
+                for x in rect.x..rect.x + rect.width {
+                    assert!(
+                        cells.insert((x, y), rect.cell).is_none(),
+                        "overlap at {x},{y}"
+                    );
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn empty_grid_has_no_rects() {
+        assert!(merge_cells(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn solid_block_is_one_rect() {
+        let cells = grid(&["#".repeat(20).as_str(); 10]);
+        assert_eq!(
+            merge_cells(&cells),
+            vec![TileRect {
+                x: 0,
+                y: 0,
+                width: 20,
+                height: 10,
+                cell: Cell::FULL
+            }]
+        );
+    }
+
+    #[test]
+    fn l_shape_is_two_rects() {
+        let cells = grid(&["#...", "#...", "####"]);
+        let rects = merge_cells(&cells);
+        assert_eq!(rects.len(), 2);
+        assert_eq!(covered(&rects), cells);
+    }
+
+    #[test]
+    fn partial_cells_do_not_stack() {
+        let cells = grid(&["===", "==="]);
+        let rects = merge_cells(&cells);
+        assert_eq!(rects.len(), 2);
+        assert!(rects.iter().all(|rect| rect.height == 1 && rect.width == 3));
+        assert_eq!(covered(&rects), cells);
+    }
+
+    #[test]
+    fn mixed_heights_split_the_row() {
+        let cells = grid(&["^###=="]);
+        let rects = merge_cells(&cells);
+        assert_eq!(
+            rects
+                .iter()
+                .map(|r| (r.x, r.width, r.cell.fill))
+                .collect::<Vec<_>>(),
+            vec![(0, 1, 0.25), (1, 3, 1.0), (4, 2, 0.5)]
+        );
+    }
+
+    #[test]
+    fn quarter_run_into_full_cells_keeps_full_height() {
+        let cells = grid(&["^####"]);
+        let rects = merge_cells(&cells);
+        assert_eq!(rects.len(), 2);
+        assert_eq!(rects[1].bounds([16.0, 16.0]), ([8.0, -8.0], [72.0, 8.0]));
+    }
+
+    #[test]
+    fn alignment_splits_runs() {
+        let cells = grid(&["__^^"]);
+        let rects = merge_cells(&cells);
+        assert_eq!(rects.len(), 2);
+        assert_eq!(covered(&rects), cells);
+    }
+
+    #[test]
+    fn partial_bounds_hug_their_edge() {
+        let bottom = TileRect {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 1,
+            cell: Cell::new(0.25, Align::Bottom),
+        };
+        let top = TileRect {
+            cell: Cell::new(0.5, Align::Top),
+            ..bottom
+        };
+        assert_eq!(bottom.bounds([16.0, 16.0]), ([-8.0, -8.0], [24.0, -4.0]));
+        assert_eq!(top.bounds([16.0, 16.0]), ([-8.0, 0.0], [24.0, 8.0]));
+    }
+
+    #[test]
+    fn half_tile_hangs_from_the_top_of_its_cell() {
+        let cell = Cell::from_tile_data("half_collider").unwrap();
+        let cells = HashMap::from([((2, 3), cell)]);
+        let rects = merge_cells(&cells);
+        assert_eq!(rects.len(), 1);
+        // Cell (2, 3) spans 24..40 across and 40..56 up.
+        assert_eq!(rects[0].bounds([16.0, 16.0]), ([24.0, 48.0], [40.0, 56.0]));
+    }
+
+    #[test]
+    fn tile_data_tags() {
+        assert_eq!(Cell::from_tile_data("collider"), Some(Cell::FULL));
+        assert_eq!(
+            Cell::from_tile_data("half_collider"),
+            Cell::from_tile_data("half_collider_top")
+        );
+        assert_eq!(
+            Cell::from_tile_data("quarter_collider"),
+            Some(Cell::new(0.25, Align::Top))
+        );
+        assert_eq!(
+            Cell::from_tile_data("quarter_collider_bottom"),
+            Some(Cell::new(0.25, Align::Bottom))
+        );
+        assert_eq!(Cell::from_tile_data("one_way"), None);
+    }
+
+    #[test]
+    fn slope_runs_share_a_hull_when_held_up() {
+        let up = Slope::from_tile_data("slope_45_up").unwrap();
+        let slopes = HashMap::from([((0, 0), up), ((1, 1), up), ((2, 2), up)]);
+        assert_eq!(merge_slopes(&slopes, &HashMap::new()).len(), 3);
+        let ground = grid(&["..#", ".##"]);
+        let merged = merge_slopes(&slopes, &ground);
+        assert_eq!(merged.len(), 1);
+        assert!(merged[0].contains(&[0.0, 0.0]) && merged[0].contains(&[3.0, 3.0]));
+    }
+
+    #[test]
+    fn shallow_slope_halves_join_within_a_row() {
+        let low = Slope::from_tile_data("slope_26_down_high").unwrap();
+        let high = Slope::from_tile_data("slope_26_down_low").unwrap();
+        let slopes = HashMap::from([((4, 0), low), ((5, 0), high)]);
+        assert_eq!(merge_slopes(&slopes, &HashMap::new()).len(), 1);
+        assert_eq!(
+            Slope::from_tile_data("slope_22_up_low"),
+            Slope::from_tile_data("slope_26_up_low")
+        );
+        assert_eq!(Slope::from_tile_data("slope_30_up"), None);
+    }
+
+    #[test]
+    fn every_cell_covered_exactly_once() {
+        let cells = grid(&["##..##==", "####.#^^", "#.######", "###=#..#", "########"]);
+        let rects = merge_cells(&cells);
+        assert_eq!(covered(&rects), cells);
+        assert!(rects.len() < cells.len() / 2);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::camera::CameraFollow;
+use crate::entities::player::PlayerMarker;
+use crate::input::{action_toggle_active, Action};
+use crate::movement_profile::{
+    ActiveMovementProfile, Autostep, ControllerTuning, MovementProfile, MovementProfiles,
+};
+use crate::player_movement::{Crouch, Distance, Jump, Run, WallJump};
+use crate::{storage, GameState};
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::egui::{self, Slider, Ui};
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPlugin};
+use bevy_rapier2d::prelude::{KinematicCharacterController, RapierConfiguration};
+use std::ops::RangeInclusive;
+
+// Live movement tuning, toggled with 4. Everything edited here is a MovementProfile, so it can be written
+// out and pasted into assets/movement.profiles.ron as a new preset.
+
+pub struct TuningPanelPlugin;
+impl Plugin for TuningPanelPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_systems(
+            Update,
+            tuning_panel
+                .run_if(in_state(GameState::InGame))
+                .run_if(action_toggle_active(false, Action::ToggleTuning)),
+        );
+    }
+}
+
+/// Where "Export" writes the current values, as a profile file with a single "custom" preset.
+pub const EXPORT_PATH: &str = "custom.profiles.ron";
+
+pub fn tuning_panel(
+    mut contexts: EguiContexts,
+    mut player: Query<
+        (
+            &mut Jump,
+            &mut Run,
+            &mut WallJump,
+            &mut Crouch,
+            &mut KinematicCharacterController,
+        ),
+        With<PlayerMarker>,
+    >,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut camera_follow: ResMut<CameraFollow>,
+    active: Res<ActiveMovementProfile>,
+    mut status: Local<String>,
+) {
+    let Ok((mut jump, mut run, mut wall_jump, mut crouch, mut controller)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    let mut profile = MovementProfile::from_components(
+        &jump,
+        &run,
+        &wall_jump,
+        &crouch,
+        ControllerTuning::from_controller(&controller),
+        camera_follow.clone(),
+        rapier_config.gravity.y,
+    );
+    let defaults = MovementProfile::default();
+    let mut changed = false;
+
+    egui::Window::new("Tuning").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!(
+            "Started from: {}",
+            active.0.as_deref().unwrap_or(MovementProfiles::DEFAULT)
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Reset all").clicked() {
+                profile = defaults.clone();
+                changed = true;
+            }
+            if ui.button("Export").clicked() {
+                let profiles = MovementProfiles(
+                    [("custom".to_string(), profile.clone())]
+                        .into_iter()
+                        .collect(),
+                );
+                storage::save_ron(EXPORT_PATH, &profiles);
+                *status = format!("Saved {}", EXPORT_PATH);
+            }
+        });
+        if !status.is_empty() {
+            ui.label(status.as_str());
+        }
+
+        ui.collapsing("Jump", |ui| {
+            changed |= meters(
+                ui,
+                "apex height",
+                &mut profile.jump_apex_height,
+                0.5..=12.0,
+                " m",
+            );
+            changed |= slider(
+                ui,
+                "time to apex",
+                &mut profile.jump_time_to_apex,
+                0.05..=1.5,
+                " s",
+            );
+            changed |= meters(
+                ui,
+                "min height",
+                &mut profile.jump_min_height,
+                0.0..=12.0,
+                " m",
+            );
+            changed |= slider(
+                ui,
+                "release cutoff",
+                &mut profile.jump_release_cutoff,
+                0.0..=1.0,
+                "x",
+            );
+            changed |= slider(
+                ui,
+                "fall gravity",
+                &mut profile.jump_fall_gravity_multiplier,
+                0.5..=5.0,
+                "x",
+            );
+            changed |= meters(
+                ui,
+                "terminal velocity",
+                &mut profile.jump_terminal_velocity,
+                1.0..=60.0,
+                " m/s",
+            );
+            changed |= slider(
+                ui,
+                "coyote time",
+                &mut profile.jump_coyote_time,
+                0.0..=0.5,
+                " s",
+            );
+            changed |= meters(
+                ui,
+                "apex hang below",
+                &mut profile.jump_apex_hang_speed,
+                0.0..=10.0,
+                " m/s",
+            );
+            changed |= slider(
+                ui,
+                "apex hang gravity",
+                &mut profile.jump_apex_hang_gravity,
+                0.0..=1.0,
+                "x",
+            );
+            if ui.button("Reset").clicked() {
+                profile.jump_apex_height = defaults.jump_apex_height.clone();
+                profile.jump_time_to_apex = defaults.jump_time_to_apex;
+                profile.jump_min_height = defaults.jump_min_height.clone();
+                profile.jump_release_cutoff = defaults.jump_release_cutoff;
+                profile.jump_fall_gravity_multiplier = defaults.jump_fall_gravity_multiplier;
+                profile.jump_terminal_velocity = defaults.jump_terminal_velocity.clone();
+                profile.jump_coyote_time = defaults.jump_coyote_time;
+                profile.jump_apex_hang_speed = defaults.jump_apex_hang_speed.clone();
+                profile.jump_apex_hang_gravity = defaults.jump_apex_hang_gravity;
+                changed = true;
+            }
+        });
+        ui.collapsing("Run", |ui| {
+            changed |= meters(
+                ui,
+                "base speed",
+                &mut profile.run_base_speed,
+                0.0..=30.0,
+                " m/s",
+            );
+            changed |= meters(
+                ui,
+                "max speed",
+                &mut profile.run_max_speed,
+                0.0..=30.0,
+                " m/s",
+            );
+            changed |= slider(
+                ui,
+                "time to max speed",
+                &mut profile.run_time_for_max_speed,
+                0.05..=5.0,
+                " s",
+            );
+            changed |= slider(
+                ui,
+                "speed curve",
+                &mut profile.run_speed_curve,
+                0.1..=4.0,
+                "",
+            );
+            changed |= meters(
+                ui,
+                "acceleration",
+                &mut profile.run_acceleration,
+                1.0..=200.0,
+                " m/s²",
+            );
+            changed |= meters(
+                ui,
+                "deceleration",
+                &mut profile.run_deceleration,
+                1.0..=200.0,
+                " m/s²",
+            );
+            changed |= meters(
+                ui,
+                "skid deceleration",
+                &mut profile.run_skid_deceleration,
+                1.0..=300.0,
+                " m/s²",
+            );
+            changed |= meters(
+                ui,
+                "skid above",
+                &mut profile.run_skid_speed,
+                0.0..=20.0,
+                " m/s",
+            );
+            changed |= slider(
+                ui,
+                "ground control",
+                &mut profile.run_ground_control,
+                0.0..=2.0,
+                "x",
+            );
+            changed |= slider(
+                ui,
+                "air control",
+                &mut profile.run_air_control,
+                0.0..=2.0,
+                "x",
+            );
+            changed |= meters(
+                ui,
+                "air deceleration",
+                &mut profile.run_air_deceleration,
+                0.0..=100.0,
+                " m/s²",
+            );
+            if ui.button("Reset").clicked() {
+                profile.run_base_speed = defaults.run_base_speed.clone();
+                profile.run_max_speed = defaults.run_max_speed.clone();
+                profile.run_time_for_max_speed = defaults.run_time_for_max_speed;
+                profile.run_speed_curve = defaults.run_speed_curve;
+                profile.run_acceleration = defaults.run_acceleration.clone();
+                profile.run_deceleration = defaults.run_deceleration.clone();
+                profile.run_skid_deceleration = defaults.run_skid_deceleration.clone();
+                profile.run_skid_speed = defaults.run_skid_speed.clone();
+                profile.run_ground_control = defaults.run_ground_control;
+                profile.run_air_control = defaults.run_air_control;
+                profile.run_air_deceleration = defaults.run_air_deceleration.clone();
+                changed = true;
+            }
+        });
+        ui.collapsing("Wall jump", |ui| {
+            changed |= meters(
+                ui,
+                "slide speed",
+                &mut profile.wall_slide_speed,
+                0.0..=20.0,
+                " m/s",
+            );
+            changed |= slider(ui, "angle", &mut profile.wall_jump_angle, 10.0..=90.0, "°");
+            changed |= slider(
+                ui,
+                "input lockout",
+                &mut profile.wall_jump_lockout,
+                0.0..=0.5,
+                " s",
+            );
+            if ui.button("Reset").clicked() {
+                profile.wall_slide_speed = defaults.wall_slide_speed.clone();
+                profile.wall_jump_angle = defaults.wall_jump_angle;
+                profile.wall_jump_lockout = defaults.wall_jump_lockout;
+                changed = true;
+            }
+        });
+        ui.collapsing("Crouch", |ui| {
+            changed |= slider(ui, "height", &mut profile.crouch_height, 0.25..=1.0, "x");
+            changed |= meters(
+                ui,
+                "walk speed",
+                &mut profile.crouch_walk_speed,
+                0.0..=10.0,
+                " m/s",
+            );
+            changed |= meters(
+                ui,
+                "slide above",
+                &mut profile.crouch_slide_speed,
+                0.0..=20.0,
+                " m/s",
+            );
+            changed |= meters(
+                ui,
+                "slide deceleration",
+                &mut profile.crouch_slide_deceleration,
+                0.0..=100.0,
+                " m/s²",
+            );
+            if ui.button("Reset").clicked() {
+                profile.crouch_height = defaults.crouch_height;
+                profile.crouch_walk_speed = defaults.crouch_walk_speed.clone();
+                profile.crouch_slide_speed = defaults.crouch_slide_speed.clone();
+                profile.crouch_slide_deceleration = defaults.crouch_slide_deceleration.clone();
+                changed = true;
+            }
+        });
+        ui.collapsing("Character controller", |ui| {
+            changed |= controller_ui(ui, &mut profile.controller);
+            if ui.button("Reset").clicked() {
+                profile.controller = defaults.controller.clone();
+                changed = true;
+            }
+        });
+        ui.collapsing("World gravity", |ui| {
+            changed |= slider(ui, "gravity", &mut profile.gravity, -1000.0..=0.0, " px/s²");
+            if ui.button("Reset").clicked() {
+                profile.gravity = defaults.gravity;
+                changed = true;
+            }
+        });
+        ui.collapsing("Camera follow", |ui| {
+            changed |= slider(
+                ui,
+                "dead zone x",
+                &mut profile.camera.dead_zone.x,
+                0.0..=128.0,
+                " px",
+            );
+            changed |= slider(
+                ui,
+                "dead zone y",
+                &mut profile.camera.dead_zone.y,
+                0.0..=128.0,
+                " px",
+            );
+            changed |= slider(ui, "catch up", &mut profile.camera.speed, 0.1..=20.0, " /s");
+            if ui.button("Reset").clicked() {
+                profile.camera = defaults.camera.clone();
+                changed = true;
+            }
+        });
+    });
+
+    // Only write back on edits so change detection stays quiet otherwise.
+    if changed {
+        profile.apply(
+            &mut jump,
+            &mut run,
+            &mut wall_jump,
+            &mut crouch,
+            &mut controller,
+        );
+        rapier_config.gravity.y = profile.gravity;
+        *camera_follow = profile.camera;
+    }
+}
+
+fn controller_ui(ui: &mut Ui, tuning: &mut ControllerTuning) -> bool {
+    let mut changed = false;
+    changed |= slider(ui, "offset", &mut tuning.offset, 0.0..=0.2, " rel");
+    changed |= slider(
+        ui,
+        "normal nudge",
+        &mut tuning.normal_nudge_factor,
+        0.0..=0.01,
+        "",
+    );
+    changed |= ui.checkbox(&mut tuning.slide, "slide").changed();
+    changed |= slider(
+        ui,
+        "max climb angle",
+        &mut tuning.max_slope_climb_angle,
+        0.0..=90.0,
+        "°",
+    );
+    changed |= slider(
+        ui,
+        "min slide angle",
+        &mut tuning.min_slope_slide_angle,
+        0.0..=90.0,
+        "°",
+    );
+    changed |= optional(
+        ui,
+        "snap to ground",
+        &mut tuning.snap_to_ground,
+        2.0,
+        |ui, snap| slider(ui, "distance", snap, 0.0..=16.0, " px"),
+    );
+    changed |= optional(
+        ui,
+        "autostep",
+        &mut tuning.autostep,
+        Autostep::default(),
+        |ui, autostep| {
+            slider(
+                ui,
+                "max height",
+                &mut autostep.max_height,
+                0.0..=1.0,
+                " rel",
+            ) | slider(ui, "min width", &mut autostep.min_width, 0.0..=1.0, " rel")
+                | ui.checkbox(&mut autostep.include_dynamic_bodies, "onto dynamic bodies")
+                    .changed()
+        },
+    );
+    changed |= ui
+        .checkbox(
+            &mut tuning.apply_impulse_to_dynamic_bodies,
+            "push dynamic bodies",
+        )
+        .changed();
+    changed |= optional(
+        ui,
+        "custom mass",
+        &mut tuning.custom_mass,
+        1.0,
+        |ui, mass| slider(ui, "mass", mass, 0.0..=100.0, ""),
+    );
+    changed
+}
+
+fn slider(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut f32,
+    range: RangeInclusive<f32>,
+    suffix: &str,
+) -> bool {
+    ui.add(Slider::new(value, range).text(label).suffix(suffix))
+        .changed()
+}
+
+/// Distances are edited in meters whatever they were written in.
+fn meters(
+    ui: &mut Ui,
+    label: &str,
+    distance: &mut Distance,
+    range: RangeInclusive<f32>,
+    suffix: &str,
+) -> bool {
+    let mut value = distance.to_meters();
+    let changed = slider(ui, label, &mut value, range, suffix);
+    if changed {
+        *distance = Distance::Meters(value);
+    }
+    changed
+}
+
+/// A checkbox turning the value on or off, with its own widgets underneath while it's on.
+fn optional<T>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<T>,
+    enabled: T,
+    add_contents: impl FnOnce(&mut Ui, &mut T) -> bool,
+) -> bool {
+    let mut on = value.is_some();
+    let mut changed = ui.checkbox(&mut on, label).changed();
+    match (on, value.is_some()) {
+        (true, false) => *value = Some(enabled),
+        (false, true) => *value = None,
+        _ => {}
+    }
+    if let Some(value) = value {
+        ui.indent(label, |ui| changed |= add_contents(ui, value));
+    }
+    changed
+}
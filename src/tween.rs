@@ -0,0 +1,254 @@
+use crate::animation::{Easing, Interpolation};
+use bevy::prelude::*;
+
+// Tweens blend a value from start to end over time and write it into a component on the same entity,
+// see Tweenable for which. They remove themselves when done and trigger TweenCompleted on the entity,
+// so `commands.entity(e).observe(..)` can chain something on the end.
+
+pub struct TweenPlugin;
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        add_tween_systems::<Transform>(app);
+        add_tween_systems::<Vec3>(app);
+        add_tween_systems::<Quat>(app);
+        add_tween_systems::<Color>(app);
+    }
+}
+fn add_tween_systems<T: Tweenable>(app: &mut App) {
+    app.add_systems(Update, (animate_tweens::<T>, animate_tween_sequences::<T>));
+}
+
+/// A value a tween can drive, and where it goes on the entity.
+pub trait Tweenable: Interpolation<Self> + Clone + Send + Sync + 'static {
+    type Target: Component;
+    fn apply(&self, target: &mut Self::Target);
+}
+impl Tweenable for Transform {
+    type Target = Transform;
+    fn apply(&self, target: &mut Transform) {
+        *target = *self;
+    }
+}
+/// Translation.
+impl Tweenable for Vec3 {
+    type Target = Transform;
+    fn apply(&self, target: &mut Transform) {
+        target.translation = *self;
+    }
+}
+/// Rotation.
+impl Tweenable for Quat {
+    type Target = Transform;
+    fn apply(&self, target: &mut Transform) {
+        target.rotation = *self;
+    }
+}
+/// Sprite tint.
+impl Tweenable for Color {
+    type Target = Sprite;
+    fn apply(&self, target: &mut Sprite) {
+        target.color = *self;
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    #[default]
+    Never,
+    Times(u32), // extra passes after the first
+    Forever,
+}
+
+/// Triggered on an entity when its tween or tween sequence finishes.
+#[derive(Event)]
+pub struct TweenCompleted;
+
+#[derive(Component, Clone, Debug)]
+pub struct Tween<T> {
+    pub start: T,
+    pub end: T,
+    pub duration: f32, // seconds per pass
+    pub easing: Easing,
+    pub delay: f32, // seconds before the first pass, the value is left alone meanwhile
+    pub repeat: Repeat,
+    pub yoyo: bool, // every other pass runs from end back to start
+    elapsed: f32,   // including the delay
+}
+impl<T: Tweenable> Tween<T> {
+    pub fn new(start: T, end: T, duration: f32) -> Self {
+        Self {
+            start,
+            end,
+            duration: duration.max(f32::EPSILON),
+            easing: Easing::Linear,
+            delay: 0.0,
+            repeat: Repeat::Never,
+            yoyo: false,
+            elapsed: 0.0,
+        }
+    }
+    pub fn with_easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+    pub fn with_delay(self, delay: f32) -> Self {
+        Self { delay, ..self }
+    }
+    pub fn with_repeat(self, repeat: Repeat) -> Self {
+        Self { repeat, ..self }
+    }
+    pub fn yoyo(self) -> Self {
+        Self { yoyo: true, ..self }
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+        // Forever never finishes, keep the clock small without changing which way a yoyo is going.
+        if self.repeat == Repeat::Forever && self.elapsed > self.delay + 2.0 * self.duration {
+            self.elapsed -= 2.0 * self.duration;
+        }
+    }
+    pub fn started(&self) -> bool {
+        self.elapsed >= self.delay
+    }
+    fn passes(&self) -> Option<u32> {
+        match self.repeat {
+            Repeat::Never => Some(1),
+            Repeat::Times(times) => Some(times + 1),
+            Repeat::Forever => None,
+        }
+    }
+    pub fn finished(&self) -> bool {
+        self.passes()
+            .is_some_and(|passes| self.overshoot(passes) >= 0.0)
+    }
+    /// Seconds past the end of the last pass, negative before it.
+    fn overshoot(&self, passes: u32) -> f32 {
+        self.elapsed - self.delay - passes as f32 * self.duration
+    }
+    /// Eased progress from start to end.
+    pub fn progress(&self) -> f32 {
+        let time = (self.elapsed - self.delay).max(0.0) / self.duration;
+        let (pass, t) = match self.passes() {
+            Some(passes) if self.finished() => (passes - 1, 1.0),
+            _ => (time.floor() as u32, time.fract()),
+        };
+        let backwards = self.yoyo && pass % 2 == 1;
+        self.easing.ease(if backwards { 1.0 - t } else { t })
+    }
+    pub fn value(&self) -> T {
+        self.start.linear(&self.end, self.progress())
+    }
+}
+
+/// Tweens run one after another, each starting where the last one's time ran out.
+#[derive(Component, Clone, Debug)]
+pub struct TweenSequence<T> {
+    steps: Vec<Tween<T>>,
+    current: usize,
+}
+impl<T: Tweenable> TweenSequence<T> {
+    pub fn new(steps: impl Into<Vec<Tween<T>>>) -> Self {
+        Self {
+            steps: steps.into(),
+            current: 0,
+        }
+    }
+    pub fn then(mut self, tween: Tween<T>) -> Self {
+        self.steps.push(tween);
+        self
+    }
+    pub fn tick(&mut self, seconds: f32) {
+        let Some(step) = self.steps.get_mut(self.current) else {
+            return;
+        };
+        step.tick(seconds);
+        while self.current + 1 < self.steps.len() {
+            let step = &self.steps[self.current];
+            let Some(passes) = step.passes().filter(|_| step.finished()) else {
+                break;
+            };
+            let left_over = step.overshoot(passes);
+            self.current += 1;
+            self.steps[self.current].tick(left_over);
+        }
+    }
+    pub fn finished(&self) -> bool {
+        self.steps
+            .last()
+            .is_none_or(|last| self.current + 1 == self.steps.len() && last.finished())
+    }
+    pub fn value(&self) -> Option<T> {
+        let step = self.steps.get(self.current)?;
+        step.started().then(|| step.value())
+    }
+}
+
+pub fn animate_tweens<T: Tweenable>(
+    mut tweens: Query<(Entity, &mut Tween<T>, &mut T::Target)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut tween, mut target) in tweens.iter_mut() {
+        tween.tick(time.delta_seconds());
+        if !tween.started() {
+            continue;
+        }
+        tween.value().apply(&mut target);
+        if tween.finished() {
+            commands.entity(entity).remove::<Tween<T>>();
+            commands.trigger_targets(TweenCompleted, entity);
+        }
+    }
+}
+
+pub fn animate_tween_sequences<T: Tweenable>(
+    mut sequences: Query<(Entity, &mut TweenSequence<T>, &mut T::Target)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut sequence, mut target) in sequences.iter_mut() {
+        sequence.tick(time.delta_seconds());
+        if let Some(value) = sequence.value() {
+            value.apply(&mut target);
+        }
+        if sequence.finished() {
+            commands.entity(entity).remove::<TweenSequence<T>>();
+            commands.trigger_targets(TweenCompleted, entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yoyo_runs_back_on_every_other_pass() {
+        let mut tween = Tween::new(Vec3::ZERO, Vec3::X, 1.0)
+            .with_delay(0.5)
+            .with_repeat(Repeat::Times(1))
+            .yoyo();
+        tween.tick(0.25);
+        assert!(!tween.started());
+        tween.tick(0.5);
+        assert!((tween.value().x - 0.25).abs() < 1e-5);
+        tween.tick(1.0);
+        assert!((tween.value().x - 0.75).abs() < 1e-5);
+        assert!(!tween.finished());
+        tween.tick(1.0);
+        assert!(tween.finished());
+        assert_eq!(tween.value(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn sequence_carries_time_into_the_next_step() {
+        let mut sequence = TweenSequence::new([Tween::new(Vec3::ZERO, Vec3::X, 1.0)])
+            .then(Tween::new(Vec3::X, Vec3::Y, 1.0));
+        sequence.tick(1.5);
+        let value = sequence.value().unwrap();
+        assert!(value.distance(Vec3::new(0.5, 0.5, 0.0)) < 1e-5);
+        assert!(!sequence.finished());
+        sequence.tick(0.5);
+        assert!(sequence.finished());
+    }
+}